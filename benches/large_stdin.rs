@@ -0,0 +1,62 @@
+//! Pipes a large input through `mk` and compares its throughput to writing the same bytes straight
+//! to disk. Run with `cargo bench`.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const TOTAL: usize = 512 * 1024 * 1024;
+const CHUNK: usize = 1024 * 1024;
+
+fn main() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let chunk = (0..CHUNK).map(|i| i as u8).collect::<Vec<_>>();
+
+    let disk = time(|| {
+        let mut file = std::fs::File::create(dir.path().join("direct.bin"))?;
+        for _ in 0..TOTAL / CHUNK {
+            file.write_all(&chunk)?;
+        }
+        file.sync_all()?;
+        Ok(())
+    })?;
+    report("direct write", disk);
+
+    for buffer_size in ["8K", "64K", "1M", "8M"] {
+        let name = format!("piped-{buffer_size}.bin");
+        let piped = time(|| {
+            let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_mk"))
+                .args(["--buffer-size", buffer_size, "--journal", "journal", &name])
+                .current_dir(dir.path())
+                .stdin(std::process::Stdio::piped())
+                .spawn()?;
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            for _ in 0..TOTAL / CHUNK {
+                stdin.write_all(&chunk)?;
+            }
+            drop(stdin);
+            anyhow::ensure!(child.wait()?.success(), "mk failed");
+            std::fs::File::open(dir.path().join(&name))?.sync_all()?;
+            Ok(())
+        })?;
+        report(&format!("mk --buffer-size {buffer_size}"), piped);
+
+        if buffer_size == "1M" {
+            anyhow::ensure!(
+                piped < disk * 2,
+                "mk took {piped:?} with the default buffer, more than twice the {disk:?} to write directly"
+            );
+        }
+    }
+    Ok(())
+}
+
+fn time(f: impl FnOnce() -> anyhow::Result<()>) -> anyhow::Result<Duration> {
+    let start = Instant::now();
+    f()?;
+    Ok(start.elapsed())
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let rate = TOTAL as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{name:<24} {elapsed:>10.2?} {rate:>8.0} MiB/s");
+}
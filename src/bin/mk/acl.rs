@@ -0,0 +1,179 @@
+use std::path::Path;
+
+/// ACL entries to grant with `--acl`, like `user:alice:rw-,group:dev:r--`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Acl(Vec<Entry>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    tag: Tag,
+    /// The user or group named by the entry, or empty for the owning one.
+    qualifier: String,
+    read: bool,
+    write: bool,
+    execute: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tag {
+    User,
+    Group,
+    Mask,
+    Other,
+}
+
+/// Parses comma-separated entries in `setfacl` form, with tags spelled out or abbreviated.
+pub fn parse(s: &str) -> anyhow::Result<Acl> {
+    s.split(',')
+        .map(|entry| {
+            let invalid = || {
+                anyhow::anyhow!("Invalid ACL entry {entry}, expected something like user:alice:rw-")
+            };
+            let (tag, rest) = entry.trim().split_once(':').ok_or_else(invalid)?;
+            let (qualifier, perms) = rest.rsplit_once(':').unwrap_or(("", rest));
+            let tag = match tag {
+                "u" | "user" => Tag::User,
+                "g" | "group" => Tag::Group,
+                "m" | "mask" => Tag::Mask,
+                "o" | "other" => Tag::Other,
+                _ => return Err(invalid()),
+            };
+            let valid = perms.len() <= 3
+                && perms.chars().all(|c| "rwx-".contains(c))
+                && (qualifier.is_empty() || matches!(tag, Tag::User | Tag::Group));
+            if !valid {
+                return Err(invalid());
+            }
+            Ok(Entry {
+                tag,
+                qualifier: qualifier.to_string(),
+                read: perms.contains('r'),
+                write: perms.contains('w'),
+                execute: perms.contains('x'),
+            })
+        })
+        .collect::<anyhow::Result<_>>()
+        .map(Acl)
+}
+
+impl std::fmt::Display for Acl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, entry) in self.0.iter().enumerate() {
+            let tag = match entry.tag {
+                Tag::User => "user",
+                Tag::Group => "group",
+                Tag::Mask => "mask",
+                Tag::Other => "other",
+            };
+            let bit = |set: bool, c: char| if set { c } else { '-' };
+            write!(
+                f,
+                "{}{tag}:{}:{}{}{}",
+                if i == 0 { "" } else { "," },
+                entry.qualifier,
+                bit(entry.read, 'r'),
+                bit(entry.write, 'w'),
+                bit(entry.execute, 'x')
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds the `acl` entries to `path` with `setfacl`.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn apply(path: &Path, acl: &Acl) -> anyhow::Result<()> {
+    use anyhow::Context;
+    run(std::process::Command::new("setfacl")
+        .arg("-m")
+        .arg(acl.to_string())
+        .arg("--")
+        .arg(path))
+    .with_context(|| format!("Unable to set ACL {acl} on {}", path.display()))
+}
+
+/// Adds the `acl` entries to `path` with `chmod +a`, which only takes named users and groups.
+#[cfg(target_os = "macos")]
+pub fn apply(path: &Path, acl: &Acl) -> anyhow::Result<()> {
+    use anyhow::Context;
+    for entry in &acl.0 {
+        anyhow::ensure!(
+            !entry.qualifier.is_empty(),
+            "ACLs on macOS only take named user and group entries, not {}",
+            Acl(vec![entry.clone()])
+        );
+        let mut perms = Vec::new();
+        if path.is_dir() {
+            perms.extend(entry.read.then_some("list"));
+            perms.extend(
+                entry
+                    .write
+                    .then_some("add_file,add_subdirectory,delete_child"),
+            );
+            perms.extend(entry.execute.then_some("search"));
+        } else {
+            perms.extend(entry.read.then_some("read"));
+            perms.extend(entry.write.then_some("write,append"));
+            perms.extend(entry.execute.then_some("execute"));
+        }
+        if perms.is_empty() {
+            continue;
+        }
+        let tag = if entry.tag == Tag::User {
+            "user"
+        } else {
+            "group"
+        };
+        run(std::process::Command::new("chmod")
+            .arg("+a")
+            .arg(format!(
+                "{tag}:{} allow {}",
+                entry.qualifier,
+                perms.join(",")
+            ))
+            .arg(path))
+        .with_context(|| format!("Unable to set ACL {acl} on {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn apply(_: &Path, _: &Acl) -> anyhow::Result<()> {
+    anyhow::bail!("--acl is only supported on Linux and macOS")
+}
+
+/// Runs `command`, failing with its stderr.
+#[cfg(unix)]
+fn run(command: &mut std::process::Command) -> anyhow::Result<()> {
+    let output = command.output()?;
+    anyhow::ensure!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_entries() -> anyhow::Result<()> {
+        let acl = parse("u:alice:rw-, group:dev:r,o::---,mask:rwx")?;
+
+        assert_eq!(
+            acl.to_string(),
+            "user:alice:rw-,group:dev:r--,other::---,mask::rwx"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        assert!(parse("alice:rw-").is_err());
+        assert!(parse("user:alice:rwz").is_err());
+        assert!(parse("other:bob:r--").is_err());
+        assert!(parse("").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+/// Splits a `--batch` line or `MK_OPTS` into arguments like a POSIX shell would, without
+/// expanding anything.
+/// Single quotes keep everything literal, double quotes and bare words take backslash escapes,
+/// and a `#` starting a word comments out the rest of the line.
+pub fn split(line: &str) -> anyhow::Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(arg.take()),
+            '#' if arg.is_none() => break,
+            '\'' => {
+                let arg = arg.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => anyhow::bail!("Unclosed ' in {line}"),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => arg.push(c),
+                            Some(c) => arg.extend(['\\', c]),
+                            None => anyhow::bail!("Unclosed \" in {line}"),
+                        },
+                        Some(c) => arg.push(c),
+                        None => anyhow::bail!("Unclosed \" in {line}"),
+                    }
+                }
+            }
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Trailing \\ in {line}"))?;
+                arg.get_or_insert_default().push(escaped);
+            }
+            c => arg.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_like_a_shell() -> anyhow::Result<()> {
+        assert_eq!(
+            split(r#"-x 'my scripts/run.sh' "say \"hi\".txt" a\ b '' # comment"#)?,
+            ["-x", "my scripts/run.sh", "say \"hi\".txt", "a b", ""]
+        );
+        assert_eq!(split("  # only a comment")?, Vec::<String>::new());
+        assert_eq!(split("issue#12.md")?, ["issue#12.md"]);
+        assert!(split("'unclosed").is_err());
+        Ok(())
+    }
+}
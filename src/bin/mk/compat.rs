@@ -0,0 +1,287 @@
+use anyhow::Context;
+use clap::Parser;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Tools `mk` can stand in for, taking their flags instead of its own.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    Mkdir,
+    Touch,
+}
+
+/// The tool to imitate and its arguments, from `--compat <TOOL>` as the first argument or from
+/// `mk` being run through a link named after the tool.
+pub fn detect(args: &[OsString]) -> Option<(Tool, Vec<OsString>)> {
+    let (program, rest) = args.split_first()?;
+    let tool = |name: &str| clap::ValueEnum::from_str(name, false).ok();
+    let by_name = Path::new(program)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(tool);
+    if let Some(tool) = by_name {
+        return Some((tool, args.to_vec()));
+    }
+
+    let (tool, rest) = match rest.first()?.to_str()? {
+        "--compat" => (tool(rest.get(1)?.to_str()?)?, &rest[2..]),
+        flag => (tool(flag.strip_prefix("--compat=")?)?, &rest[1..]),
+    };
+    let name = clap::ValueEnum::to_possible_value(&tool)?.get_name().into();
+    Some((
+        tool,
+        std::iter::once(name).chain(rest.iter().cloned()).collect(),
+    ))
+}
+
+/// An invocation of a tool `mk` stands in for.
+pub enum Invocation {
+    Mkdir(Mkdir),
+    Touch(Touch),
+}
+
+/// Make directories, like `mkdir`.
+#[derive(Parser)]
+#[command(name = "mkdir")]
+pub struct Mkdir {
+    /// Make parent directories as needed, and accept directories that already exist.
+    #[clap(short, long)]
+    parents: bool,
+
+    /// Set the mode of the made directories to the octal <MODE>.
+    #[clap(short, long, value_name = "MODE", value_parser = crate::mode::parse)]
+    mode: Option<u32>,
+
+    /// Print each directory made.
+    #[clap(short, long)]
+    verbose: bool,
+
+    #[clap(required = true)]
+    dirs: Vec<PathBuf>,
+}
+
+/// Make files or update their timestamps, like `touch`.
+#[derive(Parser)]
+#[command(name = "touch")]
+pub struct Touch {
+    /// Only change the access time.
+    #[clap(short = 'a')]
+    access: bool,
+
+    /// Only change the modification time.
+    #[clap(short = 'm')]
+    modification: bool,
+
+    /// Don't make missing files.
+    #[clap(short = 'c', long)]
+    no_create: bool,
+
+    /// Use <DATE>, like `2024-05-06 12:30` or `@1715000000`, instead of the current time.
+    #[clap(short, long, value_name = "DATE", value_parser = parse_date)]
+    date: Option<SystemTime>,
+
+    /// Use <STAMP> in the form [[CC]YY]MMDDhhmm[.ss] instead of the current time.
+    #[clap(short = 't', value_name = "STAMP", value_parser = parse_stamp, conflicts_with = "date")]
+    stamp: Option<SystemTime>,
+
+    /// Use the times of <FILE> instead of the current time.
+    #[clap(short, long, value_name = "FILE", conflicts_with_all = ["date", "stamp"])]
+    reference: Option<PathBuf>,
+
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+}
+
+impl Invocation {
+    pub fn try_parse_from(tool: Tool, args: Vec<OsString>) -> Result<Self, clap::Error> {
+        Ok(match tool {
+            Tool::Mkdir => Self::Mkdir(Mkdir::try_parse_from(args)?),
+            Tool::Touch => Self::Touch(Touch::try_parse_from(args)?),
+        })
+    }
+
+    /// The `mk` flags that make missing paths the way the tool would.
+    pub fn flags(&self) -> Vec<&'static str> {
+        match self {
+            Self::Mkdir(mkdir) if mkdir.parents => vec!["--directory"],
+            Self::Mkdir(_) => vec!["--directory", "--no-parents"],
+            Self::Touch(_) => vec!["--file"],
+        }
+    }
+
+    /// The paths for `mk` to make. Paths the tool accepts as they are, like existing directories
+    /// for `mkdir -p`, are left out, while the ones it refuses are kept so `mk` reports them.
+    pub fn missing(&self, root: &Path) -> Vec<PathBuf> {
+        match self {
+            Self::Mkdir(mkdir) => mkdir
+                .dirs
+                .iter()
+                .filter(|dir| !(mkdir.parents && root.join(dir).is_dir()))
+                .cloned()
+                .collect(),
+            Self::Touch(touch) if touch.no_create => Vec::new(),
+            Self::Touch(touch) => touch
+                .files
+                .iter()
+                .filter(|file| !root.join(file).exists())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Does what the tool does beyond making the `made` paths, like setting modes or times.
+    pub fn finish(&self, root: &Path, made: &[PathBuf]) -> anyhow::Result<()> {
+        match self {
+            Self::Mkdir(mkdir) => {
+                for dir in made {
+                    if let Some(mode) = mkdir.mode {
+                        crate::mode::apply(&root.join(dir), Some(mode), 0)?;
+                    }
+                    if mkdir.verbose {
+                        println!("mkdir: created directory '{}'", dir.display());
+                    }
+                }
+                Ok(())
+            }
+            Self::Touch(touch) => touch.set_times(root),
+        }
+    }
+}
+
+impl Touch {
+    /// Sets the times of every existing file to the requested ones.
+    fn set_times(&self, root: &Path) -> anyhow::Result<()> {
+        let reference = match &self.reference {
+            Some(reference) => Some(
+                root.join(reference)
+                    .metadata()
+                    .with_context(|| format!("Unable to read times of {}", reference.display()))?,
+            ),
+            None => None,
+        };
+        let time = self.date.or(self.stamp).unwrap_or_else(SystemTime::now);
+        let (accessed, modified) = match &reference {
+            Some(reference) => (reference.accessed()?, reference.modified()?),
+            None => (time, time),
+        };
+        // Like `touch`, `-a` and `-m` together are the same as neither.
+        let both = self.access == self.modification;
+
+        let mut times = std::fs::FileTimes::new();
+        if both || self.access {
+            times = times.set_accessed(accessed);
+        }
+        if both || self.modification {
+            times = times.set_modified(modified);
+        }
+        for file in &self.files {
+            let path = root.join(file);
+            if !path.exists() {
+                continue;
+            }
+            std::fs::File::open(&path)
+                .and_then(|f| f.set_times(times))
+                .with_context(|| format!("Unable to set times of {}", file.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a `touch -d` date: `@<seconds>` since the epoch, RFC 3339, or a local
+/// `YYYY-MM-DD[ hh:mm[:ss]]`.
+pub fn parse_date(s: &str) -> anyhow::Result<SystemTime> {
+    if let Some(seconds) = s.strip_prefix('@') {
+        let seconds = seconds
+            .parse()
+            .with_context(|| format!("Invalid date {s}"))?;
+        return Ok(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds));
+    }
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(date.into());
+    }
+    let date = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .with_context(|| format!("Invalid date {s}, expected something like 2024-05-06 12:30"))?;
+    local(date).with_context(|| format!("Date {s} doesn't exist in the local time zone"))
+}
+
+/// Parses a `touch -t` stamp like `[[CC]YY]MMDDhhmm[.ss]` in local time.
+fn parse_stamp(s: &str) -> anyhow::Result<SystemTime> {
+    let invalid = || anyhow::anyhow!("Invalid stamp {s}, expected [[CC]YY]MMDDhhmm[.ss]");
+    let (digits, seconds) = s.split_once('.').unwrap_or((s, "00"));
+    if !digits.bytes().all(|b| b.is_ascii_digit()) || seconds.len() != 2 {
+        return Err(invalid());
+    }
+    let year = match digits.len() {
+        8 => chrono::Datelike::year(&chrono::Local::now()).to_string(),
+        // POSIX puts two-digit years from 69 in the 1900s.
+        10 if digits[..2] >= *"69" => format!("19{}", &digits[..2]),
+        10 => format!("20{}", &digits[..2]),
+        12 => digits[..4].to_string(),
+        _ => return Err(invalid()),
+    };
+    let rest = &digits[digits.len() - 8..];
+    let date =
+        chrono::NaiveDateTime::parse_from_str(&format!("{year}{rest}{seconds}"), "%Y%m%d%H%M%S")
+            .map_err(|_| invalid())?;
+    local(date).ok_or_else(invalid)
+}
+
+fn local(date: chrono::NaiveDateTime) -> Option<SystemTime> {
+    date.and_local_timezone(chrono::Local)
+        .earliest()
+        .map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<OsString> {
+        s.split(' ').map(Into::into).collect()
+    }
+
+    #[test]
+    fn detects_tool() {
+        assert_eq!(
+            detect(&args("/usr/local/bin/mkdir -p a")),
+            Some((Tool::Mkdir, args("/usr/local/bin/mkdir -p a")))
+        );
+        assert_eq!(
+            detect(&args("mk --compat touch -c a")),
+            Some((Tool::Touch, args("touch -c a")))
+        );
+        assert_eq!(
+            detect(&args("mk --compat=mkdir a")),
+            Some((Tool::Mkdir, args("mkdir a")))
+        );
+        assert_eq!(detect(&args("mk a --compat mkdir")), None);
+        assert_eq!(detect(&args("mk --compat ls")), None);
+    }
+
+    #[test]
+    fn parses_stamps_and_dates() -> anyhow::Result<()> {
+        assert_eq!(
+            parse_stamp("202405061230.15")?,
+            local(chrono::NaiveDateTime::parse_from_str(
+                "2024-05-06 12:30:15",
+                "%Y-%m-%d %H:%M:%S"
+            )?)
+            .expect("exists")
+        );
+        assert_eq!(parse_stamp("6905061230")?, parse_date("1969-05-06 12:30")?);
+        assert_eq!(
+            parse_date("@1715000000")?,
+            parse_date("2024-05-06T12:53:20Z")?
+        );
+        assert!(parse_stamp("0506").is_err());
+        Ok(())
+    }
+}
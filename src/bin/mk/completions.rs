@@ -0,0 +1,121 @@
+use clap_complete::engine::ValueCompleter;
+use clap_complete::env::Shells;
+use clap_complete::{CompletionCandidate, PathCompleter};
+use mk::config::Config;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Shells `mk completions` can register for.
+pub const SHELLS: [&str; 5] = ["bash", "elvish", "fish", "powershell", "zsh"];
+
+/// Writes the script that registers `mk`'s completions with `shell`. The shell then asks `mk`
+/// itself for candidates, so values from the config stay current.
+pub fn register(shell: &str, out: &mut dyn std::io::Write) -> anyhow::Result<()> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(shell)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported shell {shell}"))?;
+    completer.write_registration("COMPLETE", "mk", "mk", "mk", out)?;
+    Ok(())
+}
+
+/// Completes `--profile` from the profiles in the user and project configs.
+pub fn profiles(current: &OsStr) -> Vec<CompletionCandidate> {
+    profile_names(&load_config(), current)
+}
+
+fn profile_names(config: &Config, current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    config
+        .profile
+        .keys()
+        .filter(|name| name.starts_with(&*current))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Completes `--template` from files under the working directory and snippets in the library.
+pub fn templates(current: &OsStr) -> Vec<CompletionCandidate> {
+    let mut candidates = PathCompleter::file().complete(current);
+    if let Ok(dir) = std::env::current_dir()
+        && let Ok(library) = crate::snippets::library(&load_config(), &dir)
+    {
+        candidates.extend(snippet_names(&library, current));
+    }
+    candidates
+}
+
+fn snippet_names(library: &Path, current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let Ok(entries) = std::fs::read_dir(library) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(&*current))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| CompletionCandidate::new(name).help(Some("snippet".into())))
+        .collect()
+}
+
+/// The config `mk` would run with from the current directory, or defaults if it can't be read.
+fn load_config() -> Config {
+    let user = Config::default_path()
+        .and_then(|path| Config::load(&path).ok())
+        .unwrap_or_default();
+    let project = std::env::current_dir()
+        .ok()
+        .and_then(|dir| Config::discover(&dir).ok().flatten());
+    match project {
+        Some(project) => user.overlay(project),
+        None => user,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registers_builtin_shells() -> anyhow::Result<()> {
+        for shell in SHELLS {
+            let mut script = Vec::new();
+            register(shell, &mut script)?;
+            assert!(String::from_utf8(script)?.contains("COMPLETE"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn completes_matching_profiles() -> anyhow::Result<()> {
+        let config: Config = toml::from_str("[profile.script]\n[profile.service]\n[profile.web]")?;
+
+        let names = profile_names(&config, OsStr::new("s"))
+            .into_iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["script", "service"]);
+        Ok(())
+    }
+
+    #[test]
+    fn completes_matching_snippets() -> anyhow::Result<()> {
+        let library = tempfile::tempdir()?;
+        std::fs::write(library.path().join("post.md"), "")?;
+        std::fs::write(library.path().join("page.html"), "")?;
+        std::fs::write(library.path().join("readme.md"), "")?;
+        std::fs::create_dir(library.path().join("partials"))?;
+
+        let names = snippet_names(library.path(), OsStr::new("p"))
+            .into_iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["page.html", "post.md"]);
+        Ok(())
+    }
+}
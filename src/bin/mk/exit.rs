@@ -0,0 +1,56 @@
+/// Exit codes for each kind of failure. They are stable, so scripts can branch on them.
+pub const FAILURE: u8 = 1;
+pub const ALREADY_EXISTS: u8 = 2;
+pub const TYPE_CONFLICT: u8 = 3;
+pub const PERMISSION_DENIED: u8 = 4;
+pub const INVALID_ARGUMENTS: u8 = 5;
+pub const REFUSED_PATH: u8 = 6;
+pub const HOOK_FAILED: u8 = 7;
+
+/// Arguments that can't be used together or don't fit the paths given. They exit like clap's own
+/// parse errors.
+#[derive(thiserror::Error, Debug)]
+#[error("{0}")]
+pub struct Usage(pub String);
+
+/// The exit code for the first failure in `e`'s chain that has one of its own.
+pub fn code(e: &anyhow::Error) -> u8 {
+    for cause in e.chain() {
+        if let Some(e) = cause.downcast_ref::<mk::Error>() {
+            match e {
+                mk::Error::AlreadyExists(_) | mk::Error::CaseCollision { .. } => {
+                    return ALREADY_EXISTS;
+                }
+                mk::Error::TypeConflict { .. }
+                | mk::Error::ExistsAsOtherKind { .. }
+                | mk::Error::ParentIsFile { .. }
+                | mk::Error::DirectoryNotEmpty(_)
+                | mk::Error::StdinForDirectory(_) => return TYPE_CONFLICT,
+                mk::Error::PermissionDenied(_) => return PERMISSION_DENIED,
+                mk::Error::StdinWithSize(_)
+                | mk::Error::StdinWithFrom(_)
+                | mk::Error::CopyOntoItself(_) => {
+                    return INVALID_ARGUMENTS;
+                }
+                mk::Error::OutsideRoot { .. }
+                | mk::Error::TooDeep { .. }
+                | mk::Error::NameTooLong { .. }
+                | mk::Error::PathTooLong { .. }
+                | mk::Error::ReservedName(_) => return REFUSED_PATH,
+                _ => {}
+            }
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>()
+            && e.kind() == std::io::ErrorKind::PermissionDenied
+        {
+            return PERMISSION_DENIED;
+        }
+        if cause.is::<crate::hooks::Failed>() {
+            return HOOK_FAILED;
+        }
+        if cause.is::<clap::Error>() || cause.is::<Usage>() {
+            return INVALID_ARGUMENTS;
+        }
+    }
+    FAILURE
+}
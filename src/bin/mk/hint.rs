@@ -0,0 +1,32 @@
+/// Suggests the flags that would get past `e`, for failures the library reports without knowing
+/// the command line.
+pub fn for_error(e: &anyhow::Error) -> Option<String> {
+    let e = e
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<mk::Error>())?;
+    Some(match e {
+        mk::Error::ExistsAsOtherKind {
+            existing,
+            wanted,
+            has_entries,
+            inferred,
+            ..
+        } => {
+            let mut hint = match has_entries {
+                true => format!("Use -o -r to replace it and everything in it with a {wanted}"),
+                false => format!("Use -o to replace it with a {wanted}"),
+            };
+            if *inferred {
+                let flag = if *existing == "directory" { "-d" } else { "-f" };
+                hint += &format!(", or {flag} if the existing {existing} is what you meant");
+            }
+            hint
+        }
+        mk::Error::ParentIsFile { .. } => "Use --overwrite-parents to replace it".into(),
+        mk::Error::DirectoryNotEmpty(_) => "Use --recursive to replace it".into(),
+        mk::Error::ContentsTooLong(_) => "Raise --max-bytes to allow more".into(),
+        mk::Error::TooDeep { .. } => "Raise --max-depth to allow it".into(),
+        mk::Error::ReservedName(_) => "Use --force to make it anyway".into(),
+        _ => return None,
+    })
+}
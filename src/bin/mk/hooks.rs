@@ -0,0 +1,152 @@
+use anyhow::Context;
+use mk::config::Hook;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// When a hook runs relative to creating its path.
+#[derive(Clone, Copy, Debug)]
+pub enum Stage {
+    Pre,
+    Post,
+}
+
+/// A hook that exited unsuccessfully, which `mk` reports with its own exit code.
+#[derive(thiserror::Error, Debug)]
+#[error("Hook `{command}` failed for {} with {status}", path.display())]
+pub struct Failed {
+    pub command: String,
+    pub path: PathBuf,
+    pub status: std::process::ExitStatus,
+}
+
+/// Runs the `stage` command of every hook whose pattern matches `path`, relative to `root`, in
+/// `root`. Hook output goes to stderr, keeping stdout for `mk`'s own.
+pub fn run(
+    hooks: &BTreeMap<String, Hook>,
+    stage: Stage,
+    root: &Path,
+    path: &Path,
+) -> anyhow::Result<()> {
+    // Matches `build/` as `build`.
+    let path = path.components().collect::<PathBuf>();
+    for (pattern, hook) in hooks {
+        let command = match stage {
+            Stage::Pre => &hook.pre,
+            Stage::Post => &hook.post,
+        };
+        let Some(command) = command else {
+            continue;
+        };
+        if !matches(pattern, &path)? {
+            continue;
+        }
+
+        tracing::debug!(pattern, command, ?stage, path = %path.display(), "running hook");
+        let status = shell(command, &path)
+            .current_dir(root)
+            .stdout(std::io::stderr())
+            .status()
+            .with_context(|| format!("Unable to run hook `{command}`"))?;
+        if !status.success() {
+            return Err(Failed {
+                command: command.clone(),
+                path,
+                status,
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// The pattern of the first hook that would run for `path`.
+pub fn matching<'a>(
+    hooks: &'a BTreeMap<String, Hook>,
+    path: &Path,
+) -> anyhow::Result<Option<&'a str>> {
+    let path = path.components().collect::<PathBuf>();
+    for (pattern, hook) in hooks {
+        if (hook.pre.is_some() || hook.post.is_some()) && matches(pattern, &path)? {
+            return Ok(Some(pattern));
+        }
+    }
+    Ok(None)
+}
+
+fn matches(pattern: &str, path: &Path) -> anyhow::Result<bool> {
+    Ok(globset::Glob::new(pattern)
+        .with_context(|| format!("Invalid hook pattern {pattern}"))?
+        .compile_matcher()
+        .is_match(path))
+}
+
+/// A shell running `command` with `{path}` standing for `path`. On unix the path is passed as an
+/// argument rather than spliced into the command, so it needs no quoting.
+#[cfg(unix)]
+fn shell(command: &str, path: &Path) -> std::process::Command {
+    let mut shell = std::process::Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(command.replace("{path}", "\"$1\""))
+        .arg("mk")
+        .arg(path);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str, path: &Path) -> std::process::Command {
+    let mut shell = std::process::Command::new("cmd");
+    shell
+        .arg("/C")
+        .arg(command.replace("{path}", &format!("\"{}\"", path.display())));
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn hooks(config: &str) -> anyhow::Result<BTreeMap<String, Hook>> {
+        Ok(toml::from_str::<mk::Config>(config)?.hooks)
+    }
+
+    #[test]
+    fn runs_matching_hooks() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let hooks = hooks(
+            r#"
+            [hooks."**/*.rs"]
+            pre = "echo pre {path} >> log"
+            post = "echo post {path} >> log"
+
+            [hooks."migrations/**"]
+            post = "echo migration {path} >> log"
+            "#,
+        )?;
+
+        run(&hooks, Stage::Pre, dir.path(), Path::new("src/it's.rs"))?;
+        run(&hooks, Stage::Post, dir.path(), Path::new("src/it's.rs"))?;
+        run(&hooks, Stage::Post, dir.path(), Path::new("migrations/1/"))?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("log"))?,
+            "pre src/it's.rs\npost src/it's.rs\nmigration migrations/1\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_failing_hooks() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let hooks = hooks("[hooks.\"*.md\"]\npost = \"exit 3\"")?;
+
+        let err = run(&hooks, Stage::Post, dir.path(), Path::new("notes.md")).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Hook `exit 3` failed for notes.md")
+        );
+        assert!(err.is::<Failed>());
+        run(&hooks, Stage::Post, dir.path(), Path::new("notes.txt"))?;
+        Ok(())
+    }
+}
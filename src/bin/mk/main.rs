@@ -0,0 +1,4065 @@
+mod acl;
+mod batch;
+mod compat;
+mod completions;
+mod exit;
+mod hint;
+mod hooks;
+mod mode;
+#[cfg(feature = "object-storage")]
+mod object;
+mod plugin;
+mod remote;
+mod shell;
+mod size;
+mod snippets;
+mod split;
+mod summary;
+mod tree;
+
+use anyhow::Context;
+use clap::{CommandFactory, Parser};
+use clap_complete::ArgValueCompleter;
+use mk::config::{Config, Kind};
+use mk::{expand, fill, git, names, platform};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(version, about, subcommand_negates_reqs = true)]
+struct Options {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Force the created entry to be a file.
+    #[clap(short, long)]
+    file: bool,
+
+    /// Force the created entry to be a directory.
+    #[clap(short, long)]
+    directory: bool,
+
+    /// Overwrite existing entries.
+    #[clap(short, long)]
+    overwrite: bool,
+
+    /// Only make each path if it is missing or older than <REFERENCE>, replacing it if it is
+    /// older. <REFERENCE> is a file, or a date like `2024-05-06 12:30` or `@1715000000`.
+    #[clap(long, value_name = "REFERENCE", conflicts_with_all = ["overwrite", "append"])]
+    if_newer: Option<String>,
+
+    /// When --if-newer's reference was last modified.
+    #[clap(skip)]
+    reference_time: Option<std::time::SystemTime>,
+
+    /// Add stdin to the end of an existing file instead of failing, making it if it is missing.
+    #[clap(
+        long,
+        conflicts_with_all = ["overwrite", "directory", "size", "split_on"]
+    )]
+    append: bool,
+
+    /// Force the created file to be executable.
+    #[clap(short = 'x', long)]
+    executable: bool,
+
+    /// Don't force the created file to be executable, even if the config or --type asks for it.
+    /// Extensions like `.sh` still make files executable unless --no-auto-exec is given too.
+    #[clap(long, overrides_with = "executable")]
+    no_executable: bool,
+
+    /// Make a sparse file of <SIZE> bytes, like 512, 4K, or 2GiB. Implies --file.
+    #[clap(long, value_name = "SIZE", value_parser = size::parse)]
+    size: Option<u64>,
+
+    /// Reserve real blocks for --size instead of making a sparse file.
+    #[clap(long, requires = "size", conflicts_with = "fill")]
+    preallocate: bool,
+
+    /// Fill the --size bytes with zero, random, or a repeated byte pattern like 0xAB.
+    #[clap(long, value_name = "FILL", requires = "size", value_parser = fill::parse)]
+    fill: Option<fill::Fill>,
+
+    /// Buffer stdin writes in chunks of <SIZE>, like 64K or 8M.
+    #[clap(long, value_name = "SIZE", value_parser = size::parse, default_value = "1M")]
+    buffer_size: u64,
+
+    /// Abort and remove the file if stdin has more than <SIZE> bytes.
+    #[clap(long, value_name = "SIZE", value_parser = size::parse)]
+    max_bytes: Option<u64>,
+
+    /// Also copy stdin to stdout, like `tee`.
+    #[clap(long, conflicts_with = "size")]
+    tee: bool,
+
+    /// Split stdin into files under the target directory at lines like `<MARKER> name.ext`.
+    #[clap(
+        long,
+        value_name = "MARKER",
+        allow_hyphen_values = true,
+        conflicts_with_all = ["files_from", "size", "tee"]
+    )]
+    split_on: Option<String>,
+
+    /// Name the file after a digest of stdin, filling placeholders like `{sha256}` in the path,
+    /// and print where it went. Files that already have the name are left as they are.
+    #[clap(
+        long,
+        conflicts_with_all = [
+            "split_on", "directory", "size", "append", "line", "template", "from", "batch"
+        ]
+    )]
+    hash_name: bool,
+
+    /// Open the created file in $VISUAL or $EDITOR and wait for it to exit. Always makes a file.
+    #[clap(short, long, conflicts_with_all = ["split_on", "directory"])]
+    edit: bool,
+
+    /// Open the created directory, or the directory containing the created file, in the file
+    /// manager.
+    #[clap(long)]
+    reveal: bool,
+
+    /// Print the created directory, or the directory containing the created file, as the last
+    /// line of stdout.
+    #[clap(long)]
+    print_dir: bool,
+
+    /// Print a `mkcd` function for <SHELL> that makes a directory and changes into it.
+    #[clap(long, value_name = "SHELL", exclusive = true)]
+    init_shell: Option<shell::Shell>,
+
+    /// Run each line of stdin as the arguments to another mk invocation, all journaled together.
+    ///
+    /// Lines are split like a shell would, without expanding variables or globs, and `#` starts
+    /// a comment. They share this invocation's --config and --profile unless they set their own.
+    #[clap(
+        long,
+        conflicts_with_all = ["paths", "files_from", "split_on", "daily", "tee", "edit"]
+    )]
+    batch: bool,
+
+    /// Open today's note, making it first with front matter if it doesn't exist yet.
+    ///
+    /// The note's path comes from `daily` in the config, and defaults to ~/notes/%Y/%m/%d.md.
+    #[clap(long, conflicts_with_all = ["paths", "files_from", "split_on", "size"])]
+    daily: bool,
+
+    /// Fill the created file from <FILE>, replacing variables like {{name}} or {{name:default}}.
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["size", "split_on"],
+        add = ArgValueCompleter::new(completions::templates)
+    )]
+    template: Option<PathBuf>,
+
+    /// Make a <LANG> source file, adding its extension and starting it from its template or
+    /// shebang, like `--type rust src/parser` for src/parser.rs.
+    ///
+    /// Languages come built in, like rust, python, or bash, and from `types` in the config.
+    #[clap(long = "type", value_name = "LANG", conflicts_with = "directory")]
+    type_name: Option<String>,
+
+    #[clap(skip)]
+    language: Option<mk::language::Language>,
+
+    /// Start new source files with minimal built-in boilerplate, like `fn main() {}` in
+    /// src/main.rs or `set -euo pipefail` under a shell script's shebang.
+    ///
+    /// Templates, including those for --type, take precedence.
+    #[clap(long)]
+    boilerplate: bool,
+
+    /// Don't add boilerplate, even if the config asks for it.
+    #[clap(long, overrides_with = "boilerplate")]
+    no_boilerplate: bool,
+
+    /// Start a Markdown file with front matter in <FORMAT>, holding a title from its name, today's
+    /// date, and the fields from the config.
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "yaml",
+        conflicts_with_all = ["size", "split_on"]
+    )]
+    frontmatter: Option<mk::frontmatter::Format>,
+
+    /// Read contents from stdin even when it is a terminal, until end of input.
+    #[clap(long)]
+    stdin: bool,
+
+    /// Never read stdin, even when it isn't a terminal, like under CI runners that leave it open.
+    #[clap(long, overrides_with = "stdin")]
+    no_stdin: bool,
+
+    /// Print the <ALGORITHM> digest of the bytes written to each file, like `sha256sum` does.
+    #[clap(long, value_name = "ALGORITHM", conflicts_with_all = ["size", "directory"])]
+    checksum: Option<mk::checksum::Algorithm>,
+
+    /// Copy the contents of <FILE> into the created file, sharing its blocks on copy-on-write
+    /// filesystems like Btrfs, XFS, and APFS so even huge seeds copy instantly.
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "size", "directory", "append", "template", "line", "frontmatter", "verify",
+            "checksum", "max_bytes", "tee", "split_on"
+        ]
+    )]
+    from: Option<PathBuf>,
+
+    /// Only make the file if its contents have the <CHECKSUM>, like sha256:<hex>. Contents are
+    /// staged and moved into place once they match, so nothing is left behind otherwise.
+    #[clap(
+        long,
+        value_name = "CHECKSUM",
+        value_parser = mk::checksum::parse,
+        conflicts_with_all = ["size", "directory", "append"]
+    )]
+    verify: Option<mk::checksum::Checksum>,
+
+    /// Check that contents are valid <FORMAT> before writing them, reporting where they aren't.
+    /// JSON, YAML, and TOML files are checked by their extension without it.
+    #[clap(long, value_name = "FORMAT", conflicts_with_all = ["size", "directory", "from"])]
+    validate: Option<mk::validate::Format>,
+
+    /// Write structured contents without checking them.
+    #[clap(long, conflicts_with = "validate")]
+    no_validate: bool,
+
+    /// Write <TEXT> as a line of the created file, in place of stdin. Repeatable.
+    #[clap(
+        long,
+        value_name = "TEXT",
+        conflicts_with_all = ["directory", "size", "split_on", "template", "batch"]
+    )]
+    line: Vec<String>,
+
+    /// End each --line with <ENDING> instead of the one in .editorconfig or the platform's.
+    #[clap(long, value_name = "ENDING", requires = "line")]
+    line_ending: Option<LineEnding>,
+
+    /// Set the template variable <KEY> to <VALUE>. Repeatable.
+    #[clap(long, value_name = "KEY=VALUE", value_parser = parse_var)]
+    var: Vec<(String, String)>,
+
+    /// Fail on template variables without a value instead of prompting for them.
+    #[clap(long)]
+    no_input: bool,
+
+    #[clap(skip)]
+    interactive: bool,
+
+    /// Answers to template prompts, so each variable is asked for once per invocation.
+    #[clap(skip)]
+    answers: std::cell::RefCell<std::collections::BTreeMap<String, String>>,
+
+    /// Run the `mk-<NAME>` plugin on PATH to transform paths and generate contents. Repeatable.
+    #[clap(long, value_name = "NAME")]
+    plugin: Vec<String>,
+
+    /// Set the permissions of the created entry to the octal <MODE>, like 640 or 2775, instead
+    /// of the defaults and -x.
+    #[clap(long, value_name = "MODE", value_parser = mode::parse)]
+    mode: Option<u32>,
+
+    /// Copy the permissions, and on unix the group, of the created entry's parent directory.
+    ///
+    /// Files only keep the execute bits they would have had anyway.
+    #[clap(long, conflicts_with = "mode")]
+    inherit: bool,
+
+    /// Create entries with the octal umask <MASK>, like 027, instead of the process's.
+    #[clap(long, value_name = "MASK", value_parser = mode::parse)]
+    umask: Option<u32>,
+
+    /// Set the setgid bit on the created directory, so entries made in it take its group.
+    #[clap(long)]
+    setgid: bool,
+
+    /// Set the sticky bit on the created directory, so only owners can remove entries in it.
+    #[clap(long)]
+    sticky: bool,
+
+    /// Grant the ACL <ENTRIES> on the created entry, like `user:alice:rw-,group:dev:r--`.
+    ///
+    /// Uses setfacl on Linux and `chmod +a` on macOS, which only takes named users and groups.
+    #[clap(long, value_name = "ENTRIES", value_parser = acl::parse)]
+    acl: Option<acl::Acl>,
+
+    /// Don't infer that files are executable from their extension.
+    #[clap(long)]
+    no_auto_exec: bool,
+
+    /// Fail instead of creating missing parent directories, like `mkdir` without `-p`.
+    #[clap(long)]
+    no_parents: bool,
+
+    /// Allow --overwrite to replace a non-empty directory with a file.
+    #[clap(short, long, requires = "overwrite")]
+    recursive: bool,
+
+    /// Replace existing files that are in the way of parent directories.
+    #[clap(long)]
+    overwrite_parents: bool,
+
+    /// Write an empty .gitkeep into every newly created directory.
+    #[clap(long)]
+    gitkeep: bool,
+
+    /// Don't write .gitkeep files, even if the config asks for them.
+    #[clap(long, overrides_with = "gitkeep")]
+    no_gitkeep: bool,
+
+    /// Stage the created entry with `git add`.
+    #[clap(long)]
+    git_add: bool,
+
+    /// Don't stage created entries, even if the config asks for it.
+    #[clap(long, overrides_with = "git_add")]
+    no_git_add: bool,
+
+    /// Register created directories with zoxide, if it is installed.
+    #[clap(long)]
+    zoxide: bool,
+
+    /// Don't register created directories with zoxide, even if the config asks for it.
+    #[clap(long, overrides_with = "zoxide")]
+    no_zoxide: bool,
+
+    /// Initialize a git repository inside the created directory.
+    #[clap(long)]
+    git_init: bool,
+
+    /// Don't warn about created entries that git ignores.
+    #[clap(long)]
+    no_ignored_warning: bool,
+
+    /// Make the paths on <HOST> over ssh, as if each were given as `<HOST>:<PATH>`.
+    #[clap(long, value_name = "[USER@]HOST")]
+    host: Option<String>,
+
+    /// Run the hooks in a project .mk.toml, which are otherwise ignored. Hooks in the user config
+    /// always run.
+    #[clap(long)]
+    trust_hooks: bool,
+
+    /// Read `<HOST>:<PATH>` as a path on <HOST> over ssh even without a `<USER>@`, as scp does.
+    #[clap(long)]
+    remote: bool,
+
+    /// Run as if started in <DIR> instead of the current directory.
+    #[clap(short = 'C', value_name = "DIR")]
+    change_dir: Option<PathBuf>,
+
+    /// Make the paths in the directory holding the existing <FILE>, like
+    /// `mk --next-to src/parser.rs parser_tests.rs` for src/parser_tests.rs.
+    #[clap(long, value_name = "FILE")]
+    next_to: Option<PathBuf>,
+
+    /// Make the <KIND> companion of each path instead, like `tests/test_parser.py` for
+    /// `--companion test parser.py`, started with boilerplate for its language.
+    #[clap(long, value_name = "KIND", conflicts_with = "directory")]
+    companion: Option<Companion>,
+
+    /// Resolve the path relative to the enclosing git repository's root.
+    #[clap(long)]
+    git_root: bool,
+
+    /// Expand $VAR and ${VAR} in paths from the environment.
+    #[clap(long)]
+    expand_env: bool,
+
+    /// Turn the final component of each path into a lowercase, dash-separated slug.
+    #[clap(long)]
+    slug: bool,
+
+    /// Replace characters that are invalid in file names on any platform, including separators
+    /// inside expanded variables.
+    #[clap(long)]
+    sanitize: bool,
+
+    /// Convert paths to a consistent Unicode normalization form.
+    #[clap(long, value_name = "FORM")]
+    normalize: Option<names::Normalization>,
+
+    /// Refuse paths nested more than <N> levels deep.
+    #[clap(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Refuse paths that lead outside the working directory, whether through `..`, absolute
+    /// paths, or symlinks.
+    #[clap(long)]
+    restrict: bool,
+
+    /// Reject names that are reserved on Windows, like CON or aux.txt, even on other platforms.
+    #[clap(long)]
+    portable_names: bool,
+
+    /// Refuse names that differ only in case from an existing entry, like README.md next to
+    /// Readme.md. Always on for macOS and Windows.
+    #[clap(long)]
+    check_case: bool,
+
+    /// Warn instead of failing on reserved names.
+    #[clap(long)]
+    force: bool,
+
+    /// Read additional paths to make from <FILE>, one per line. Use '-' for stdin.
+    #[clap(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+
+    /// Paths read with --files-from are separated by NUL instead of newlines.
+    #[clap(long, requires = "files_from")]
+    null: bool,
+
+    /// Keep making the remaining paths when one fails, then report what failed.
+    #[clap(short, long)]
+    keep_going: bool,
+
+    /// Print how many directories were made, how many files were made or written to, the bytes
+    /// written, the entries skipped, and the time taken to stderr.
+    #[clap(long)]
+    summary: bool,
+
+    /// Entries that failed under --keep-going or were up to date for --if-newer, shared with the
+    /// lines of --batch.
+    #[clap(skip)]
+    skipped: std::rc::Rc<std::cell::Cell<usize>>,
+
+    /// Files made or written to, shared with the lines of --batch.
+    #[clap(skip)]
+    written: std::rc::Rc<std::cell::Cell<summary::Written>>,
+
+    /// Entries made, with the paths they were asked for by, to check against git's ignore rules
+    /// once everything is made. Shared with the lines of --batch.
+    #[clap(skip)]
+    made: std::rc::Rc<std::cell::RefCell<Vec<(PathBuf, PathBuf)>>>,
+
+    /// Record created entries in <FILE> so `mk undo` can remove them.
+    ///
+    /// Defaults to a journal in the user data directory.
+    #[clap(long, value_name = "FILE")]
+    journal: Option<PathBuf>,
+
+    /// Log why entries are made the way they are at <LEVEL>, like debug or mk=trace, to stderr.
+    ///
+    /// Defaults to the MK_LOG environment variable, and to no logging without it.
+    #[clap(long, value_name = "LEVEL")]
+    log_level: Option<String>,
+
+    /// Read defaults from <FILE>.
+    ///
+    /// Defaults to config.toml in the mk directory of the user config directory.
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Act as <TOOL>, taking its flags instead of mk's. Must be the first argument.
+    ///
+    /// Running mk through a link named `mkdir` or `touch` does the same.
+    #[clap(long, value_name = "TOOL")]
+    compat: Option<compat::Tool>,
+
+    /// Apply the settings of the named profile from the config.
+    #[clap(long, value_name = "NAME", add = ArgValueCompleter::new(completions::profiles))]
+    profile: Option<String>,
+
+    #[clap(skip)]
+    defaults: Config,
+
+    /// Remove everything made by this invocation if any entry fails.
+    #[clap(long, conflicts_with = "keep_going")]
+    atomic: bool,
+
+    /// The paths to make.
+    ///
+    /// Entry type is inferred from if the path has an extension or not. Paths with final item starting with '.' are inferred as directories unless configured otherwise, as are extensions like `.d` or numbers. A trailing separator always makes a directory.
+    #[clap(required_unless_present_any = ["files_from", "init_shell", "daily", "batch"])]
+    paths: Vec<PathBuf>,
+}
+
+impl Options {
+    /// Fills in the default journal and config locations.
+    fn locate_defaults(&mut self) {
+        if self.journal.is_none() {
+            self.journal = dirs::data_local_dir().map(|d| d.join("mk").join("journal"));
+        }
+        if self.config.is_none() {
+            self.config = Config::default_path();
+        }
+    }
+
+    /// Whether to read contents from stdin, which is read when it isn't a terminal unless
+    /// --stdin or --no-stdin say otherwise.
+    fn reads_stdin(&self, is_terminal: bool) -> bool {
+        !self.no_stdin && (self.stdin || !is_terminal)
+    }
+
+    /// Fills in defaults from `config` for anything not given on the command line.
+    fn apply(&mut self, config: Config) {
+        self.executable |= config.executable.unwrap_or(false) && !self.no_executable;
+        self.gitkeep |= config.gitkeep.unwrap_or(false) && !self.no_gitkeep;
+        self.boilerplate |= config.boilerplate.unwrap_or(false) && !self.no_boilerplate;
+        self.git_add |= config.git_add.unwrap_or(false) && !self.no_git_add;
+        self.zoxide |= config.zoxide.unwrap_or(false) && !self.no_zoxide;
+        if !self.inherit {
+            self.mode = self.mode.or(config.mode);
+        }
+        self.defaults = config;
+    }
+
+    /// The setgid and sticky bits to add to new directories.
+    fn special_bits(&self) -> u32 {
+        (if self.setgid { mode::SETGID } else { 0 }) | (if self.sticky { mode::STICKY } else { 0 })
+    }
+
+    /// A library request to make `path` as these options ask.
+    fn request(&self, path: &Path) -> anyhow::Result<mk::CreateRequest> {
+        let kind = match (self.file, self.directory) {
+            (false, false) if self.split_on.is_some() || self.hash_name || self.edit => {
+                Some(Kind::File)
+            }
+            (false, false) => None,
+            (true, false) => Some(Kind::File),
+            (false, true) => Some(Kind::Directory),
+            (true, true) => {
+                anyhow::bail!(exit::Usage("Cannot force both file and directory".into()))
+            }
+        };
+        Ok(mk::CreateRequest::new(path)
+            .kind(kind)
+            .overwrite(self.overwrite)
+            .append(self.append)
+            .recursive(self.recursive)
+            .overwrite_parents(self.overwrite_parents)
+            .parents(!self.no_parents)
+            .executable(self.executable)
+            .auto_exec(!self.no_auto_exec)
+            .gitkeep(self.gitkeep)
+            .git_init(self.git_init)
+            .size(self.size)
+            .fill(self.fill.clone())
+            .preallocate(self.preallocate)
+            .buffer_size(self.buffer_size)
+            .max_bytes(self.max_bytes)
+            .verify(self.verify.clone())
+            .from(
+                self.from
+                    .as_deref()
+                    .map(|from| expand::tilde(from, dirs::home_dir().as_deref()))
+                    .transpose()?,
+            )
+            .slug(self.slug)
+            .sanitize(self.sanitize)
+            .normalize(self.normalize)
+            .restrict(self.restrict)
+            .max_depth(self.max_depth)
+            .portable_names(self.portable_names)
+            .check_case(self.check_case)
+            .force(self.force)
+            .config(self.defaults.clone()))
+    }
+}
+
+/// Line endings for `--line`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum LineEnding {
+    Lf,
+    Crlf,
+    /// CRLF on Windows and LF elsewhere.
+    Native,
+}
+
+/// Files that go along with a source file, for `--companion`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Companion {
+    /// The test file where the language's conventions put it.
+    Test,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Make files, never guessing from the paths. Same as `mk -f`.
+    File {
+        #[clap(required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Make directories, never guessing from the paths. Same as `mk -d`.
+    Dir {
+        #[clap(required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Make a symbolic link at <LINK> pointing to <TARGET>, making the link's missing parents.
+    Link {
+        /// What the link points to, relative to the link's directory unless it is absolute.
+        target: PathBuf,
+
+        link: PathBuf,
+
+        /// Make a hard link instead, with <TARGET> relative to the working directory.
+        #[clap(long)]
+        hard: bool,
+    },
+
+    /// Make the entries in an indented outline, like the output of `tree`.
+    ///
+    /// Entries with children indented under them are directories, and the rest are inferred.
+    Tree {
+        /// Read the outline from <FILE> instead of stdin.
+        outline: Option<PathBuf>,
+    },
+
+    /// Manage the snippet library, whose snippets --template takes by name.
+    Snippets {
+        #[command(subcommand)]
+        action: snippets::Action,
+    },
+
+    /// Remove the entries made by the last invocation.
+    Undo,
+
+    /// Print a script that registers completions for <SHELL>.
+    Completions {
+        #[clap(value_parser = completions::SHELLS)]
+        shell: String,
+    },
+}
+
+fn main() -> std::process::ExitCode {
+    clap_complete::CompleteEnv::with_factory(Options::command).complete();
+
+    let args = std::env::args_os().collect::<Vec<_>>();
+    if let Some((tool, args)) = compat::detect(&args) {
+        return match compat::Invocation::try_parse_from(tool, args) {
+            Ok(invocation) => finish(start_compat(invocation)),
+            Err(e) => usage_error(e),
+        };
+    }
+
+    let args = match with_env_options(args, std::env::var("MK_OPTS").ok()) {
+        Ok(args) => args,
+        Err(e) => return finish(Err(e)),
+    };
+    match Options::try_parse_from(args) {
+        Ok(options) => finish(start(options)),
+        Err(e) => usage_error(e),
+    }
+}
+
+/// Prints a command line parsing error, which is also how help and version are printed.
+fn usage_error(e: clap::Error) -> std::process::ExitCode {
+    let _ = e.print();
+    match e.use_stderr() {
+        true => exit::INVALID_ARGUMENTS.into(),
+        false => std::process::ExitCode::SUCCESS,
+    }
+}
+
+fn finish(result: anyhow::Result<()>) -> std::process::ExitCode {
+    match result {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            if let Some(hint) = hint::for_error(&e) {
+                eprintln!("{hint}");
+            }
+            exit::code(&e).into()
+        }
+    }
+}
+
+/// Runs `mk` as invoked from the command line.
+fn start(mut options: Options) -> anyhow::Result<()> {
+    let dir = std::env::current_dir()?;
+    options.locate_defaults();
+    init_logging(options.log_level.as_deref())?;
+    let is_terminal = std::io::IsTerminal::is_terminal(&std::io::stdin());
+    let reads_stdin = options.reads_stdin(is_terminal);
+    // Prompts would compete with contents for a terminal that is being read.
+    options.interactive = is_terminal && !reads_stdin;
+
+    if reads_stdin {
+        run(dir, options, std::io::stdin().lock())
+    } else {
+        run(dir, options, &[][..])
+    }
+}
+
+/// Runs `mk` standing in for another tool.
+fn start_compat(invocation: compat::Invocation) -> anyhow::Result<()> {
+    let dir = std::env::current_dir()?;
+    init_logging(None)?;
+    run_compat(&dir, invocation, Options::locate_defaults)
+}
+
+/// Makes the paths `invocation` is missing with `mk`, then does the rest of what its tool does.
+/// Stdin is left alone, as scripts running the tool in a loop may be reading from it.
+fn run_compat(
+    root: &Path,
+    invocation: compat::Invocation,
+    prepare: impl FnOnce(&mut Options),
+) -> anyhow::Result<()> {
+    let missing = invocation.missing(root);
+    if !missing.is_empty() {
+        let args = ["mk"]
+            .into_iter()
+            .chain(invocation.flags())
+            .chain(["--"])
+            .map(std::ffi::OsString::from)
+            .chain(missing.iter().map(Into::into));
+        let mut options = Options::try_parse_from(args)?;
+        prepare(&mut options);
+        run(root, options, &[][..])?;
+    }
+    invocation.finish(root, &missing)
+}
+
+/// Sends logs at `level`, or the level in `MK_LOG`, to stderr.
+fn init_logging(level: Option<&str>) -> anyhow::Result<()> {
+    let filter = match level {
+        Some(level) => tracing_subscriber::EnvFilter::try_new(level)
+            .with_context(|| format!("Invalid log level {level}"))?,
+        None => tracing_subscriber::EnvFilter::try_from_env("MK_LOG")
+            .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("off")),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+        .without_time()
+        .init();
+    Ok(())
+}
+
+/// Parses a `--var` as `KEY=VALUE`.
+fn parse_var(s: &str) -> anyhow::Result<(String, String)> {
+    let (key, value) = s.split_once('=').context("Expected KEY=VALUE")?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Inserts the flags from `MK_OPTS` ahead of the real arguments, split and quoted like a line of
+/// `--batch`.
+fn with_env_options(
+    args: impl IntoIterator<Item = std::ffi::OsString>,
+    env_options: Option<String>,
+) -> anyhow::Result<Vec<std::ffi::OsString>> {
+    let env_options = match env_options {
+        Some(opts) => batch::split(&opts).context("Invalid MK_OPTS")?,
+        None => Vec::new(),
+    };
+    let mut args = args.into_iter();
+    Ok(args
+        .next()
+        .into_iter()
+        .chain(env_options.into_iter().map(Into::into))
+        .chain(args)
+        .collect())
+}
+
+fn run<R: std::io::Read>(root: impl AsRef<Path>, options: Options, stdin: R) -> anyhow::Result<()> {
+    let start = std::time::Instant::now();
+    let atomic = options.atomic;
+    // Commands that make nothing leave the last invocation's journal for `mk undo`.
+    let journal = options.journal.clone().filter(|_| {
+        !matches!(
+            options.command,
+            Some(Command::Undo | Command::Completions { .. } | Command::Snippets { .. })
+        )
+    });
+    let summary = options
+        .summary
+        .then(|| (options.skipped.clone(), options.written.clone()));
+    let _umask = options.umask.map(mode::umask);
+    let made = options.made.clone();
+    let mut created = Vec::new();
+    let result = make(root.as_ref(), options, stdin, &mut created);
+    warn_ignored(&made.take());
+    let result = record(result, &created, atomic, journal.as_deref());
+    if let Some((skipped, written)) = summary {
+        let summary =
+            summary::Summary::new(&created, written.get(), skipped.get(), start.elapsed());
+        eprintln!("{summary}");
+    }
+    result
+}
+
+/// Does what `options` ask, recording every entry it creates in `created`.
+fn make<R: std::io::Read>(
+    root: &Path,
+    mut options: Options,
+    stdin: R,
+    created: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let mut link = None;
+    let mut outline = None;
+    let mut snippets = None;
+    match options.command.take() {
+        Some(Command::Undo) => {
+            let journal = options.journal.context("No journal to undo from")?;
+            return undo(&journal);
+        }
+        Some(Command::Completions { shell }) => {
+            return completions::register(&shell, &mut std::io::stdout());
+        }
+        Some(Command::File { paths }) => {
+            options.file = true;
+            options.paths = paths;
+        }
+        Some(Command::Dir { paths }) => {
+            options.directory = true;
+            options.paths = paths;
+        }
+        Some(Command::Link {
+            target,
+            link: path,
+            hard,
+        }) => link = Some((target, path, hard)),
+        Some(Command::Tree { outline: file }) => outline = Some(file),
+        Some(Command::Snippets { action }) => snippets = Some(action),
+        None => {}
+    }
+
+    anyhow::ensure!(
+        options.compat.is_none(),
+        exit::Usage("--compat must be the first argument".into())
+    );
+
+    if let Some(shell) = options.init_shell {
+        print!("{}", shell::init(shell));
+        return Ok(());
+    }
+
+    let root = match &options.change_dir {
+        Some(dir) => root.join(dir),
+        None => root.to_path_buf(),
+    };
+    anyhow::ensure!(root.is_dir(), "Directory {} does not exist", root.display());
+
+    let root = if options.git_root {
+        git::find_root(&root)
+            .with_context(|| format!("{} is not inside a git repository", root.display()))?
+    } else {
+        &root
+    };
+
+    if options.batch {
+        return create_batch(root, &options, stdin, created);
+    }
+
+    let mut config = match &options.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    if let Some(mut project) = Config::discover(root)? {
+        // A checked out repository shouldn't run commands just because mk was used in it.
+        if project.has_hooks() && !options.trust_hooks {
+            eprintln!(
+                "Warning: ignoring hooks in the project {}, pass --trust-hooks to run them",
+                mk::config::PROJECT_FILE
+            );
+            project = project.without_hooks();
+        }
+        config = config.overlay(project);
+    }
+    if let Some(profile) = &options.profile {
+        config = config.with_profile(profile)?;
+    }
+    options.apply(config);
+
+    if let Some(action) = snippets {
+        let library = snippets::library(&options.defaults, root)?;
+        return snippets::run(action, &library, root, &mut std::io::stdout());
+    }
+
+    if options.daily {
+        let note = daily_note(&options.defaults)?;
+        let path = root.join(expand::tilde(&note, dirs::home_dir().as_deref())?);
+        if path.is_file() {
+            return edit(&path, &options.defaults);
+        }
+        options.edit = true;
+        if mk::frontmatter::is_markdown(&path) {
+            options.frontmatter.get_or_insert_default();
+        }
+        options.paths = vec![note];
+    }
+
+    if let Some((target, path, hard)) = link {
+        let path = expand::tilde(&path, dirs::home_dir().as_deref())?;
+        return create_link(root, &target, &path, hard, &options, created);
+    }
+
+    if let Some(reference) = &options.if_newer {
+        let path = root.join(expand::tilde(
+            Path::new(reference),
+            dirs::home_dir().as_deref(),
+        )?);
+        let time = match path.metadata() {
+            Ok(metadata) => metadata.modified()?,
+            Err(_) => compat::parse_date(reference).with_context(|| {
+                format!("No reference file {reference}, and it isn't a date either")
+            })?,
+        };
+        options.reference_time = Some(time);
+    }
+
+    if let Some(name) = &options.type_name {
+        let Some(language) = mk::language::find(name, &options.defaults.types) else {
+            let known = mk::language::builtin_names()
+                .map(str::to_string)
+                .chain(options.defaults.types.keys().cloned())
+                .collect::<std::collections::BTreeSet<_>>();
+            anyhow::bail!(exit::Usage(format!(
+                "Unknown type {name}, expected one of {}",
+                known.into_iter().collect::<Vec<_>>().join(", ")
+            )));
+        };
+        options.file = true;
+        options.executable |= language.executable.unwrap_or(false) && !options.no_executable;
+        options.language = Some(language);
+    }
+
+    let mut stdin = stdin;
+    let mut paths = options.paths.clone();
+    let mut stdin_consumed = false;
+    if let Some(file) = outline {
+        let contents = match file {
+            Some(file) if file.as_os_str() != "-" => std::fs::read_to_string(root.join(&file))
+                .with_context(|| format!("Unable to read {}", file.display()))?,
+            _ => {
+                stdin_consumed = true;
+                let mut contents = String::new();
+                stdin.read_to_string(&mut contents)?;
+                contents
+            }
+        };
+        paths.extend(tree::parse(&contents)?);
+    }
+    if let Some(list) = &options.files_from {
+        let contents = if list.as_os_str() == "-" {
+            stdin_consumed = true;
+            let mut contents = Vec::new();
+            stdin.read_to_end(&mut contents)?;
+            contents
+        } else {
+            std::fs::read(root.join(list))
+                .with_context(|| format!("Unable to read {}", list.display()))?
+        };
+        paths.extend(parse_path_list(&contents, options.null)?);
+    }
+
+    if options.edit {
+        // The editor needs the terminal, not a pipe that was meant as content.
+        let is_stdin_empty = !stdin_consumed && stdin.read(&mut [0; 1][..])? == 0;
+        anyhow::ensure!(
+            is_stdin_empty,
+            exit::Usage("Cannot open an editor when stdin has content".into())
+        );
+    }
+
+    #[cfg(feature = "object-storage")]
+    let objects = paths
+        .iter()
+        .filter_map(|path| object::parse(path))
+        .collect::<Vec<_>>();
+    #[cfg(not(feature = "object-storage"))]
+    anyhow::ensure!(
+        !paths.iter().any(|path| {
+            let path = path.to_string_lossy();
+            path.starts_with("s3://") || path.starts_with("gs://")
+        }),
+        exit::Usage(
+            "mk was built without object storage, rebuild it with --features object-storage".into()
+        )
+    );
+    #[cfg(feature = "object-storage")]
+    if !objects.is_empty() {
+        anyhow::ensure!(
+            objects.len() == paths.len(),
+            exit::Usage("Cannot mix local and object storage paths".into())
+        );
+        return create_objects(&objects, &options, stdin_consumed, stdin);
+    }
+
+    let targets = paths
+        .iter()
+        .filter_map(|path| remote::target(path, options.host.as_deref(), options.remote))
+        .collect::<Vec<_>>();
+    if !targets.is_empty() {
+        anyhow::ensure!(
+            targets.len() == paths.len(),
+            exit::Usage("Cannot mix local and remote paths".into())
+        );
+        return create_remote(root, &targets, &options, stdin_consumed, stdin);
+    }
+
+    let home = dirs::home_dir();
+    let paths = paths
+        .iter()
+        .map(|path| {
+            let path = if options.expand_env {
+                expand::env(path, |name| {
+                    let value = std::env::var(name).ok()?;
+                    Some(if options.sanitize {
+                        names::sanitize_component(&value)
+                    } else {
+                        value
+                    })
+                })?
+            } else {
+                path.clone()
+            };
+            expand::tilde(&path, home.as_deref())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let paths = match &options.next_to {
+        Some(sibling) => {
+            let sibling = expand::tilde(sibling, home.as_deref())?;
+            anyhow::ensure!(
+                root.join(&sibling).symlink_metadata().is_ok(),
+                "{} does not exist to make paths next to",
+                sibling.display()
+            );
+            let dir = sibling.parent().unwrap_or(Path::new(""));
+            paths.iter().map(|path| dir.join(path)).collect()
+        }
+        None => paths,
+    };
+
+    let paths = match options.companion {
+        Some(Companion::Test) => paths
+            .iter()
+            .map(|path| {
+                mk::language::test_path(path)
+                    .with_context(|| format!("No test file convention for {}", path.display()))
+            })
+            .collect::<anyhow::Result<_>>()?,
+        None => paths,
+    };
+
+    match &paths[..] {
+        [] => Err(exit::Usage("No paths to make".into()).into()),
+        [path] if options.split_on.is_some() => {
+            create_split_entries(root, path, &options, stdin, created)
+        }
+        [path] if options.hash_name => create_hashed_entry(root, path, &options, stdin, created),
+        _ if options.hash_name => Err(exit::Usage("--hash-name takes a single path".into()).into()),
+        [path] if !stdin_consumed => create_entry(root, path, &options, stdin, created),
+        paths => create_entries(root, paths, &options, stdin_consumed, stdin, created),
+    }
+}
+
+/// Rolls back the `created` entries if making them failed under `--atomic`, then journals what is
+/// left for `mk undo`.
+fn record(
+    result: anyhow::Result<()>,
+    created: &[PathBuf],
+    atomic: bool,
+    journal: Option<&Path>,
+) -> anyhow::Result<()> {
+    let rolled_back = result.is_err() && atomic;
+    if rolled_back {
+        remove_created(created).context("Unable to roll back created entries")?;
+    }
+    if let Some(journal) = journal {
+        write_journal(journal, if rolled_back { &[] } else { created })?;
+    }
+    result
+}
+
+/// Warns about the `made` entries that git ignores, which won't be committed.
+fn warn_ignored(made: &[(PathBuf, PathBuf)]) {
+    let paths = made
+        .iter()
+        .map(|(path, _)| path.clone())
+        .collect::<Vec<_>>();
+    let ignored = git::ignored(&paths);
+    for (_, resolved) in made.iter().filter(|(path, _)| ignored.contains(path)) {
+        eprintln!(
+            "Warning: {} is ignored by git and won't be committed",
+            resolved.display()
+        );
+    }
+}
+
+/// Fails before anything is made if `--setgid` or `--sticky` are given for a file.
+fn ensure_special_bits_fit(options: &Options, request: &mk::CreateRequest) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        options.special_bits() == 0 || !request.is_file()?,
+        exit::Usage("--setgid and --sticky only apply to directories, not files".into())
+    );
+    Ok(())
+}
+
+/// Warns when `--force` let `entry` use a name Windows reserves.
+fn warn_reserved(entry: &mk::Entry) {
+    if let Some(name) = &entry.reserved_name {
+        eprintln!("Warning: {name} is a reserved name on Windows");
+    }
+}
+
+/// Makes a link at `path` under `root` pointing to `target`, first making the link's missing
+/// parents as directories.
+fn create_link(
+    root: &Path,
+    target: &Path,
+    path: &Path,
+    hard: bool,
+    options: &Options,
+    created: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+        && !root.join(parent).is_dir()
+    {
+        let request = options.request(parent)?.kind(Kind::Directory);
+        warn_reserved(&mk::create(root, &request, &[][..], created)?);
+    }
+
+    let link = root.join(path);
+    if link.symlink_metadata().is_ok() {
+        if !options.overwrite {
+            return Err(mk::Error::AlreadyExists(path.to_path_buf()).into());
+        }
+        std::fs::remove_file(&link)
+            .with_context(|| format!("Unable to replace {}", path.display()))?;
+    }
+    let result = if hard {
+        std::fs::hard_link(root.join(target), &link)
+    } else {
+        platform::symlink(target, &link)
+    };
+    result.with_context(|| format!("Unable to link {} to {}", path.display(), target.display()))?;
+    options.written.set(options.written.get().add(0));
+    created.push(link);
+    Ok(())
+}
+
+/// Today's note for `--daily`, from the pattern in `config`.
+fn daily_note(config: &Config) -> anyhow::Result<PathBuf> {
+    use std::fmt::Write;
+
+    let pattern = config.daily.as_deref().unwrap_or("~/notes/%Y/%m/%d.md");
+    let mut note = String::new();
+    write!(note, "{}", chrono::Local::now().format(pattern))
+        .map_err(|_| anyhow::anyhow!("Invalid daily note pattern {pattern}"))?;
+    Ok(note.into())
+}
+
+/// Replaces the journal with the entries made by this invocation, or removes it if there are
+/// none, so `mk undo` never reaches back to an earlier one. Each entry is its kind, a space, and
+/// its path as raw bytes, ended by a NUL, which no path can contain.
+fn write_journal(journal: &Path, created: &[PathBuf]) -> anyhow::Result<()> {
+    if created.is_empty() {
+        return match std::fs::remove_file(journal) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Unable to clear journal {}", journal.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+    let mut contents = Vec::new();
+    for path in created {
+        // Links are removed like files, even when they point to a directory.
+        let kind = if path.is_dir() && !path.is_symlink() {
+            b'd'
+        } else {
+            b'f'
+        };
+        contents.extend([kind, b' ']);
+        contents.extend(path.as_os_str().as_encoded_bytes());
+        contents.push(0);
+    }
+
+    std::fs::create_dir_all(journal.parent().expect("journal is a file"))?;
+    std::fs::write(journal, contents)
+        .with_context(|| format!("Unable to write journal {}", journal.display()))
+}
+
+/// The path written into the journal as `bytes`.
+fn journal_path(bytes: &[u8]) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(std::ffi::OsStr::from_bytes(bytes).into())
+    }
+    // Elsewhere paths are only read back when they are valid Unicode.
+    #[cfg(not(unix))]
+    {
+        std::str::from_utf8(bytes).ok().map(PathBuf::from)
+    }
+}
+
+/// Removes the entries recorded in the journal, leaving anything that changed type or gained
+/// foreign contents since.
+fn undo(journal: &Path) -> anyhow::Result<()> {
+    let contents = match std::fs::read(journal) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("Nothing to undo")
+        }
+        result => {
+            result.with_context(|| format!("Unable to read journal {}", journal.display()))?
+        }
+    };
+
+    for entry in contents.split(|&b| b == 0).filter(|e| !e.is_empty()).rev() {
+        let (kind, path) = match entry {
+            [kind, b' ', path @ ..] => (*kind, journal_path(path)),
+            _ => (0, None),
+        };
+        let path = path.with_context(|| {
+            format!(
+                "Malformed journal entry: {}",
+                String::from_utf8_lossy(entry)
+            )
+        })?;
+
+        let result = match kind {
+            b'd' if path.is_dir() => std::fs::remove_dir(&path),
+            b'f' if path.is_file() || path.is_symlink() => std::fs::remove_file(&path),
+            _ if !path.exists() => continue,
+            _ => {
+                eprintln!("Not removing {}, its type has changed", path.display());
+                continue;
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("Not removing {}: {e}", path.display());
+        }
+    }
+
+    std::fs::remove_file(journal)?;
+    Ok(())
+}
+
+fn create_entries(
+    root: &Path,
+    paths: &[PathBuf],
+    options: &Options,
+    stdin_consumed: bool,
+    mut stdin: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let is_stdin_empty = stdin_consumed || stdin.read(&mut [0; 1][..])? == 0;
+    anyhow::ensure!(
+        is_stdin_empty,
+        exit::Usage("Cannot write stdin data to multiple entries".into())
+    );
+
+    if !options.keep_going {
+        for path in paths {
+            create_entry(root, path, options, &[][..], created)?;
+        }
+        return Ok(());
+    }
+
+    let failures = paths
+        .iter()
+        .filter_map(|path| {
+            let err = create_entry(root, path, options, &[][..], created).err()?;
+            eprintln!("{}: {err:#}", path.display());
+            Some(path)
+        })
+        .count();
+    options.skipped.set(options.skipped.get() + failures);
+    eprintln!(
+        "Made {} of {} entries, {failures} failed",
+        paths.len() - failures,
+        paths.len()
+    );
+    anyhow::ensure!(failures == 0, "Failed to make {failures} entries");
+    Ok(())
+}
+
+/// Runs each line of stdin as its own invocation under `root`, recording everything they make
+/// in `created` so the batch is journaled, or rolled back, as one.
+fn create_batch(
+    root: &Path,
+    options: &Options,
+    mut stdin: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let mut lines = String::new();
+    stdin
+        .read_to_string(&mut lines)
+        .context("Unable to read batch from stdin")?;
+
+    let mut failures = 0;
+    for (number, line) in lines.lines().enumerate() {
+        let args = batch::split(line)?;
+        if args.is_empty() {
+            continue;
+        }
+        let result = Options::try_parse_from(std::iter::once("mk".to_string()).chain(args))
+            .map_err(anyhow::Error::from)
+            .and_then(|mut line_options| {
+                anyhow::ensure!(
+                    !line_options.batch,
+                    exit::Usage("Cannot nest --batch".into())
+                );
+                line_options.journal = None;
+                line_options.config = line_options.config.or(options.config.clone());
+                line_options.profile = line_options.profile.or(options.profile.clone());
+                line_options.skipped = options.skipped.clone();
+                line_options.written = options.written.clone();
+                line_options.made = options.made.clone();
+                anyhow::ensure!(
+                    !line_options.summary,
+                    exit::Usage("--summary applies to the whole batch, not a line".into())
+                );
+                line_options.trust_hooks |= options.trust_hooks;
+                let _umask = line_options.umask.map(mode::umask);
+                let atomic = line_options.atomic;
+                let mut line_created = Vec::new();
+                let result = make(root, line_options, &[][..], &mut line_created);
+                let rolled_back = result.is_err() && atomic;
+                let result = record(result, &line_created, atomic, None);
+                if !rolled_back {
+                    created.extend(line_created);
+                }
+                result
+            })
+            .with_context(|| format!("Line {}: {line}", number + 1));
+        match result {
+            Err(e) if options.keep_going => {
+                eprintln!("{e:#}");
+                failures += 1;
+                options.skipped.set(options.skipped.get() + 1);
+            }
+            result => result?,
+        }
+    }
+    anyhow::ensure!(failures == 0, "Failed {failures} lines of the batch");
+    Ok(())
+}
+
+/// Makes a file under `dir` for each section of stdin split by `--split-on`.
+fn create_split_entries(
+    root: &Path,
+    dir: &Path,
+    options: &Options,
+    mut stdin: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let marker = options
+        .split_on
+        .as_deref()
+        .expect("only called when splitting");
+    let mut contents = Vec::new();
+    stdin.read_to_end(&mut contents)?;
+
+    for (name, section) in split::sections(&contents, marker)? {
+        create_entry(root, &dir.join(name), options, &section[..], created)?;
+    }
+    Ok(())
+}
+
+/// Makes the file named by filling the digest placeholders in `path` from the contents of stdin.
+fn create_hashed_entry(
+    root: &Path,
+    path: &Path,
+    options: &Options,
+    mut stdin: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let mut contents = Vec::new();
+    stdin.read_to_end(&mut contents)?;
+
+    let mut name = path
+        .to_str()
+        .with_context(|| format!("{} is not valid UTF-8", path.display()))?
+        .to_string();
+    for algorithm in <mk::checksum::Algorithm as clap::ValueEnum>::value_variants() {
+        let placeholder = format!("{{{}}}", algorithm);
+        if name.contains(&placeholder) {
+            let mut hasher = mk::checksum::Hasher::new(*algorithm);
+            hasher.update(&contents);
+            name = name.replace(&placeholder, &hasher.finish().to_string());
+        }
+    }
+    let hashed = PathBuf::from(name);
+    anyhow::ensure!(
+        hashed != path,
+        exit::Usage(format!(
+            "--hash-name needs a placeholder like {{sha256}} in {}",
+            path.display()
+        ))
+    );
+
+    // The name says what the contents are, so an existing file already holds them.
+    if root.join(&hashed).is_file() {
+        tracing::debug!(path = %hashed.display(), "already stored");
+    } else {
+        create_entry(root, &hashed, options, &contents[..], created)?;
+    }
+    if options.tee || options.print_dir {
+        eprintln!("{}", hashed.display());
+    } else {
+        println!("{}", hashed.display());
+    }
+    Ok(())
+}
+
+/// Removes entries made by this invocation, newest first.
+fn remove_created(created: &[PathBuf]) -> anyhow::Result<()> {
+    for path in created.iter().rev() {
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(path)
+        } else {
+            std::fs::remove_file(path)
+        };
+        match result {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Unable to remove {}", path.display()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Splits a `--files-from` list into paths, skipping blank entries.
+fn parse_path_list(contents: &[u8], null: bool) -> anyhow::Result<Vec<PathBuf>> {
+    let separator = if null { b'\0' } else { b'\n' };
+    contents
+        .split(|&b| b == separator)
+        .map(|entry| if null { entry } else { entry.trim_ascii_end() })
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| Ok(PathBuf::from(std::str::from_utf8(entry)?)))
+        .collect()
+}
+
+/// The generated start of the file at `transformed`: front matter, then `--line`s, a template,
+/// boilerplate, or a shebang, whichever applies first. Shebangs only start entries that are
+/// files.
+fn render(
+    root: &Path,
+    transformed: &Path,
+    options: &Options,
+    is_file: bool,
+    has_stdin: bool,
+    appends: bool,
+) -> anyhow::Result<Vec<u8>> {
+    let mut rendered = Vec::new();
+    if let Some(format) = options.frontmatter.filter(|_| !appends) {
+        anyhow::ensure!(
+            mk::frontmatter::is_markdown(transformed),
+            exit::Usage(format!(
+                "--frontmatter only applies to Markdown files, not {}",
+                transformed.display()
+            ))
+        );
+        let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let title = mk::frontmatter::title(transformed);
+        let frontmatter =
+            mk::frontmatter::render(format, &title, &date, &options.defaults.frontmatter);
+        rendered.extend(frontmatter.into_bytes());
+    }
+    let language = options.language.as_ref();
+    let boilerplate = match options.companion {
+        Some(Companion::Test) => mk::language::test_boilerplate(transformed),
+        None => mk::language::boilerplate(transformed)
+            .filter(|_| options.boilerplate)
+            .map(str::to_string),
+    }
+    .filter(|_| !options.directory && options.size.is_none());
+    if !options.line.is_empty() {
+        anyhow::ensure!(
+            !has_stdin,
+            exit::Usage("Cannot combine --line with stdin data".into())
+        );
+        let newline = line_ending(&root.join(transformed), options.line_ending)?;
+        for line in &options.line {
+            rendered.extend(line.as_bytes());
+            rendered.extend(newline.as_bytes());
+        }
+    } else if appends || options.from.is_some() {
+        // Existing files already have their start, and copies get theirs from --from.
+    } else if let Some(template) = &options.template {
+        anyhow::ensure!(
+            !has_stdin,
+            exit::Usage("Cannot combine --template with stdin data".into())
+        );
+        rendered.extend(render_template(root, template, transformed, options)?);
+    } else if has_stdin {
+        // Piped contents replace anything generated.
+    } else if let Some(template) = language.and_then(|l| l.template.as_ref()) {
+        let template = expand::tilde(template, dirs::home_dir().as_deref())?;
+        rendered.extend(render_template(root, &template, transformed, options)?);
+    } else if let Some(boilerplate) = boilerplate {
+        let properties = mk::editorconfig::properties(&root.join(transformed))?;
+        rendered.extend(mk::editorconfig::format(&boilerplate, &properties)?);
+    } else if let Some(shebang) = language.and_then(|l| l.shebang.as_ref()) {
+        rendered.extend(format!("{shebang}\n").into_bytes());
+    } else if is_file
+        && options.size.is_none()
+        && let Some(shebang) = options.defaults.shebang_line(transformed)?
+    {
+        rendered.extend(format!("{shebang}\n").into_bytes());
+    }
+    Ok(rendered)
+}
+
+/// The format the contents of `path` are checked as, from `--validate` or its extension.
+fn validation(path: &Path, options: &Options, appends: bool) -> Option<mk::validate::Format> {
+    options
+        .validate
+        .or_else(|| mk::validate::Format::of(path))
+        .filter(|_| !options.no_validate && !appends && !options.directory)
+}
+
+/// Fails unless `contents` for `path` are valid `format`. Files made empty are left for filling
+/// in later.
+fn validate(format: mk::validate::Format, path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    if contents.is_empty() {
+        return Ok(());
+    }
+    mk::validate::check(format, contents)
+        .with_context(|| format!("Refusing to write invalid {format} to {}", path.display()))
+}
+
+/// Makes `target` under `root`, recording every entry it creates in `created`, then does
+/// whatever else the options ask of the new entry.
+fn create_entry(
+    root: &Path,
+    target: &Path,
+    options: &Options,
+    mut stdin: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let search = std::env::var_os("PATH").unwrap_or_default();
+    let target = match &options.language {
+        Some(language) => language.path(target),
+        None => target.to_path_buf(),
+    };
+    let transformed = plugin::path(&options.plugin, &target, &search)?;
+    // Resolved up front so that hooks and generated contents see the final name.
+    let mut request = mk::resolve(&mk::RealFs, root, &options.request(&transformed)?)?;
+    let resolved = request.path().to_path_buf();
+    ensure_special_bits_fit(options, &request)?;
+    if let Some(reference) = options.reference_time
+        && let Ok(metadata) = root.join(&resolved).metadata()
+    {
+        if metadata.modified()? >= reference {
+            tracing::debug!(path = %resolved.display(), "up to date");
+            options.skipped.set(options.skipped.get() + 1);
+            return Ok(());
+        }
+        request = request.overwrite(true);
+    }
+    // Peeked so that generated contents can step aside for piped ones.
+    let mut first = [0; 1];
+    let peeked = stdin.read(&mut first)?;
+    let has_stdin = peeked != 0;
+    let stdin = std::io::Read::chain(&first[..peeked], stdin);
+
+    let appends = options.append && root.join(&resolved).is_file();
+    let rendered = render(
+        root,
+        &resolved,
+        options,
+        request.is_file()?,
+        has_stdin,
+        appends,
+    )?;
+    let validated;
+    let mut contents: Box<dyn std::io::Read + '_> =
+        Box::new(std::io::Read::chain(&rendered[..], stdin));
+    if let Some(format) = validation(&resolved, options, appends) {
+        // Buffered so that nothing is written unless all of it parses, reading one byte past
+        // --max-bytes to tell an input of exactly the limit from a longer one.
+        let mut buffered = Vec::new();
+        let limit = options.max_bytes.unwrap_or(u64::MAX);
+        use std::io::Read;
+        (&mut contents)
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut buffered)?;
+        if buffered.len() as u64 > limit {
+            return Err(mk::Error::ContentsTooLong(limit).into());
+        }
+        validate(format, &resolved, &buffered)?;
+        validated = buffered;
+        contents = Box::new(&validated[..]);
+    }
+    let mut stdin = Tee {
+        reader: mk::checksum::HashingReader {
+            reader: contents,
+            hasher: options.checksum.map(mk::checksum::Hasher::new),
+        },
+        copy: options.tee.then(std::io::stdout),
+    };
+    let hooks = &options.defaults.hooks;
+    hooks::run(hooks, hooks::Stage::Pre, root, &resolved)?;
+    let first_created = created.len();
+    let entry = mk::create(root, &request, &mut stdin, created)?;
+    warn_reserved(&entry);
+    let mut written = entry.written;
+    if let Some(stdout) = &mut stdin.copy {
+        stdout.flush()?;
+    }
+    // Plugins only generate contents for files left empty, so stdin still wins.
+    if !options.plugin.is_empty()
+        && entry.is_file
+        && options.size.is_none()
+        && entry.path.metadata()?.len() == 0
+    {
+        plugin::content(&options.plugin, &entry.path, &search)?;
+        written += entry.path.metadata()?.len();
+    }
+    if entry.is_file {
+        options.written.set(options.written.get().add(written));
+    }
+    let special = options.special_bits();
+    if options.inherit {
+        mode::inherit(&entry.path, entry.is_file)?;
+    }
+    if options.mode.is_some() || special != 0 {
+        mode::apply(&entry.path, options.mode, special)?;
+    }
+    // After the mode, as changing group bits can change the ACL mask.
+    if let Some(acl) = &options.acl {
+        acl::apply(&entry.path, acl)?;
+    }
+    hooks::run(hooks, hooks::Stage::Post, root, &entry.resolved)?;
+
+    if !options.no_ignored_warning {
+        options
+            .made
+            .borrow_mut()
+            .push((entry.path.clone(), entry.resolved.clone()));
+    }
+
+    if entry.resolved != *target {
+        // Under --tee and --print-dir, stdout carries something else.
+        if options.tee || options.print_dir {
+            eprintln!("{}", entry.resolved.display());
+        } else {
+            println!("{}", entry.resolved.display());
+        }
+    }
+    if let Some(hasher) = stdin.reader.hasher.take()
+        && entry.is_file
+    {
+        let line = format!("{}  {}", hasher.finish(), entry.resolved.display());
+        if options.tee || options.print_dir {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
+    }
+
+    let path = &entry.path;
+    let dir = if entry.is_file {
+        path.parent().expect("joined with root")
+    } else {
+        path
+    };
+
+    if options.edit {
+        edit(path, &options.defaults)?;
+    }
+
+    if options.reveal {
+        platform::reveal(dir).with_context(|| format!("Unable to reveal {}", dir.display()))?;
+    }
+
+    if options.print_dir {
+        println!("{}", dir.display());
+    }
+
+    if options.git_add {
+        // The outermost created directory holds nothing but what we just made.
+        git::add(created.get(first_created).unwrap_or(path))?;
+    }
+
+    if options.zoxide && !entry.is_file {
+        zoxide_add(path);
+    }
+
+    Ok(())
+}
+
+/// The line ending for `--line` in `path`: `ending` if given, then the one in `.editorconfig`,
+/// then the platform's.
+fn line_ending(path: &Path, ending: Option<LineEnding>) -> anyhow::Result<&'static str> {
+    let ending = match ending {
+        Some(ending) => ending,
+        None => match mk::editorconfig::properties(path)?
+            .get("end_of_line")
+            .map(String::as_str)
+        {
+            Some("crlf") => LineEnding::Crlf,
+            Some("lf") => LineEnding::Lf,
+            _ => LineEnding::Native,
+        },
+    };
+    Ok(match ending {
+        LineEnding::Lf => "\n",
+        LineEnding::Crlf => "\r\n",
+        LineEnding::Native if cfg!(windows) => "\r\n",
+        LineEnding::Native => "\n",
+    })
+}
+
+/// Renders `template` for `target`, taking variables from `--var`, the config, and the built-in
+/// `path` and `name`, then prompting for the rest. The result follows the target's
+/// `.editorconfig`.
+fn render_template(
+    root: &Path,
+    template: &Path,
+    target: &Path,
+    options: &Options,
+) -> anyhow::Result<Vec<u8>> {
+    let mut path = root.join(template);
+    if !path.exists()
+        && let Some(snippet) = snippets::library(&options.defaults, root)
+            .ok()
+            .and_then(|library| snippets::find(&library, template))
+    {
+        path = snippet;
+    }
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Unable to read template {}", template.display()))?;
+    let rendered = mk::template::render(&contents, |name, default| {
+        let given = options.var.iter().rev().find(|(key, _)| key == name);
+        if let Some((_, value)) = given {
+            return Ok(value.clone());
+        }
+        if let Some(value) = options
+            .answers
+            .borrow()
+            .get(name)
+            .or_else(|| options.defaults.vars.get(name))
+        {
+            return Ok(value.clone());
+        }
+        match name {
+            "path" => return Ok(target.display().to_string()),
+            "name" => {
+                if let Some(stem) = target.file_stem() {
+                    return Ok(stem.to_string_lossy().into_owned());
+                }
+            }
+            _ => {}
+        }
+
+        let value = if options.interactive && !options.no_input {
+            prompt(name, default)?
+        } else {
+            default
+                .with_context(|| {
+                    format!("Missing template variable {name}, set it with --var {name}=VALUE")
+                })?
+                .to_string()
+        };
+        options
+            .answers
+            .borrow_mut()
+            .insert(name.to_string(), value.clone());
+        Ok(value)
+    })?;
+    let properties = mk::editorconfig::properties(&root.join(target))?;
+    mk::editorconfig::format(&rendered, &properties)
+}
+
+/// Asks on the terminal for the template variable `name`, offering `default`. Without a default,
+/// asks again until given a value.
+fn prompt(name: &str, default: Option<&str>) -> anyhow::Result<String> {
+    loop {
+        match default {
+            Some(default) => eprint!("{name} [{default}]: "),
+            None => eprint!("{name}: "),
+        }
+        let mut line = String::new();
+        let read = std::io::stdin().read_line(&mut line)?;
+        anyhow::ensure!(read != 0, "No value given for template variable {name}");
+        match (line.trim(), default) {
+            ("", Some(default)) => return Ok(default.to_string()),
+            ("", None) => continue,
+            (value, _) => return Ok(value.to_string()),
+        }
+    }
+}
+
+/// Makes each of `targets` on its host over ssh. Entries are resolved in an in-memory filesystem
+/// first, holding the remote siblings `{n}` counts, so names, kinds, and contents come out as
+/// they would locally.
+fn create_remote(
+    root: &Path,
+    targets: &[remote::Target],
+    options: &Options,
+    stdin_consumed: bool,
+    mut stdin: impl std::io::Read,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !(options.edit
+            || options.reveal
+            || options.print_dir
+            || options.git_init
+            || options.split_on.is_some()
+            || options.size.is_some()
+            || options.append
+            || options.checksum.is_some()
+            || options.verify.is_some()
+            || options.from.is_some()
+            || options.if_newer.is_some()
+            || options.companion.is_some()
+            || options.hash_name
+            || options.inherit
+            || options.umask.is_some()
+            || options.acl.is_some()
+            || !options.plugin.is_empty()),
+        exit::Usage("Cannot use --edit, --reveal, --print-dir, --git-init, --split-on, --size, --append, --checksum, --verify, --from, --if-newer, --companion, --hash-name, --inherit, --umask, --acl, or --plugin with remote paths".into())
+    );
+    // Peeked so that generated contents can step aside for piped ones.
+    let mut first = [0; 1];
+    let peeked = if stdin_consumed {
+        0
+    } else {
+        stdin.read(&mut first)?
+    };
+    let has_stdin = peeked != 0;
+    anyhow::ensure!(
+        targets.len() == 1 || !has_stdin,
+        exit::Usage("Cannot write stdin data to multiple entries".into())
+    );
+    let mut stdin = std::io::Read::chain(&first[..peeked], stdin);
+
+    let existing = remote::Existing {
+        overwrite: options.overwrite,
+        recursive: options.recursive,
+        parents: !options.no_parents,
+    };
+    for target in targets {
+        let path = match &options.language {
+            Some(language) => language.path(&target.path),
+            None => target.path.clone(),
+        };
+        let fs = mk::MemoryFs::new();
+        if let Some(dir) = expand::counter_dir(&path) {
+            use mk::fs::Filesystem;
+
+            let siblings = Path::new("/").join(&dir);
+            fs.create_dir_all(&siblings)?;
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                &dir
+            };
+            for name in remote::list(&target.host, dir)? {
+                // Only the names matter for counting.
+                fs.create_new(&siblings.join(name))?;
+            }
+        }
+        // Whether parents exist is up to the remote host, and .gitkeep files stay local.
+        let request = options.request(&path)?.parents(true).gitkeep(false);
+        let request = mk::resolve(&fs, Path::new("/"), &request)?;
+        ensure_special_bits_fit(options, &request)?;
+        if let Some(pattern) = hooks::matching(&options.defaults.hooks, request.path())? {
+            anyhow::bail!(exit::Usage(format!(
+                "Cannot run hooks for {pattern} on remote paths"
+            )));
+        }
+        let rendered = render(
+            root,
+            request.path(),
+            options,
+            request.is_file()?,
+            has_stdin,
+            false,
+        )?;
+        let contents = std::io::Read::chain(&rendered[..], &mut stdin);
+        let entry = mk::create_in(&fs, Path::new("/"), &request, contents, &mut Vec::new())?;
+        warn_reserved(&entry);
+        let contents = fs.read(&entry.path).unwrap_or_default();
+        if let Some(format) = validation(&entry.resolved, options, false)
+            && entry.is_file
+        {
+            validate(format, &entry.resolved, &contents)?;
+        }
+        if options.tee {
+            std::io::stdout().write_all(&contents)?;
+            std::io::stdout().flush()?;
+        }
+        if entry.resolved != target.path {
+            println!("{}", target.display(&entry.resolved));
+        }
+
+        let special = options.special_bits();
+        let permissions = remote::Permissions {
+            executable: fs.is_executable(&entry.path),
+            mode: options.mode,
+            special,
+        };
+        let script = remote::script(&entry.resolved, entry.is_file, &permissions, &existing);
+        remote::run(&target.host, &script, &contents)
+            .with_context(|| format!("Unable to make {}", target.display(&entry.resolved)))?;
+    }
+    Ok(())
+}
+
+/// Uploads each of `objects` to its bucket, streaming stdin as the contents of a file. Kinds are
+/// inferred from the key as they would be locally, and directories become empty keys ending in a
+/// slash.
+#[cfg(feature = "object-storage")]
+fn create_objects(
+    objects: &[object::Object],
+    options: &Options,
+    stdin_consumed: bool,
+    mut stdin: impl std::io::Read,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !(options.edit
+            || options.reveal
+            || options.print_dir
+            || options.git_init
+            || options.split_on.is_some()
+            || options.size.is_some()
+            || options.max_bytes.is_some()
+            || options.append
+            || !options.line.is_empty()
+            || options.checksum.is_some()
+            || options.verify.is_some()
+            || options.from.is_some()
+            || options.if_newer.is_some()
+            || options.companion.is_some()
+            || options.hash_name),
+        exit::Usage("Cannot use --edit, --reveal, --print-dir, --git-init, --split-on, --size, --max-bytes, --append, --line, --checksum, --verify, --from, --if-newer, --companion, or --hash-name with object storage paths".into())
+    );
+    if objects.len() > 1 {
+        let is_stdin_empty = stdin_consumed || stdin.read(&mut [0; 1][..])? == 0;
+        anyhow::ensure!(
+            is_stdin_empty,
+            exit::Usage("Cannot write stdin data to multiple entries".into())
+        );
+    }
+
+    for object in objects {
+        anyhow::ensure!(!object.key.is_empty(), "No key to make in {object}");
+        // Contents are streamed separately, so only the name is resolved here.
+        let request = options.request(Path::new(&object.key))?.gitkeep(false);
+        let fs = mk::MemoryFs::new();
+        let entry = mk::create_in(&fs, Path::new("/"), &request, &[][..], &mut Vec::new())?;
+        warn_reserved(&entry);
+
+        let mut key = entry.resolved.to_string_lossy().into_owned();
+        if !entry.is_file && !key.ends_with('/') {
+            key.push('/');
+        }
+        let resolved = object.with_key(key);
+        if entry.resolved != Path::new(&object.key) {
+            println!("{resolved}");
+        }
+        if !options.overwrite && resolved.exists()? {
+            return Err(mk::Error::AlreadyExists(resolved.to_string().into()).into());
+        }
+
+        if entry.is_file {
+            let mut stdin = Tee {
+                reader: &mut stdin,
+                copy: options.tee.then(std::io::stdout),
+            };
+            resolved
+                .upload(&mut stdin)
+                .with_context(|| format!("Unable to upload {resolved}"))?;
+            if let Some(stdout) = &mut stdin.copy {
+                stdout.flush()?;
+            }
+        } else {
+            let is_stdin_empty = stdin_consumed || stdin.read(&mut [0; 1][..])? == 0;
+            if !is_stdin_empty {
+                return Err(mk::Error::StdinForDirectory(resolved.to_string().into()).into());
+            }
+            resolved
+                .upload(&mut &[][..])
+                .with_context(|| format!("Unable to upload {resolved}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads from `reader`, writing everything read to `copy` as well.
+struct Tee<R, W> {
+    reader: R,
+    copy: Option<W>,
+}
+
+impl<R: std::io::Read, W: std::io::Write> std::io::Read for Tee<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        if let Some(copy) = &mut self.copy {
+            copy.write_all(&buf[..read])?;
+        }
+        Ok(read)
+    }
+}
+
+/// Opens `file` in the configured editor, `$VISUAL`, or `$EDITOR`, waiting for it to exit.
+fn edit(file: &Path, config: &Config) -> anyhow::Result<()> {
+    let editor = config
+        .editor
+        .clone()
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|editor| !editor.trim().is_empty())
+        .context("No editor to open, set $EDITOR or editor in the config")?;
+    let mut words = editor.split_whitespace();
+
+    let status = std::process::Command::new(words.next().expect("editor is not blank"))
+        .args(words)
+        .arg(file)
+        .status()
+        .with_context(|| format!("Unable to run editor {editor}"))?;
+    anyhow::ensure!(status.success(), "Editor {editor} exited with {status}");
+    Ok(())
+}
+
+/// Registers `dir` with zoxide so it can be jumped to right away. Only warns on failure, and
+/// stays quiet when zoxide isn't installed or processes can't be spawned, as on WASI.
+fn zoxide_add(dir: &Path) {
+    let output = std::process::Command::new("zoxide")
+        .arg("add")
+        .arg(dir)
+        .output();
+    match output {
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::NotFound | std::io::ErrorKind::Unsupported
+            ) => {}
+        Err(e) => eprintln!("Warning: unable to run zoxide: {e}"),
+        Ok(output) if !output.status.success() => eprintln!(
+            "Warning: unable to register {} with zoxide: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Ok(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    fn run_command(cmd: &str) -> anyhow::Result<TempDir> {
+        let dir = tempfile::tempdir()?;
+        run_command_in(dir.path(), cmd)?;
+        Ok(dir)
+    }
+
+    fn run_command_in(dir: &Path, cmd: &str) -> anyhow::Result<()> {
+        let options = Options::try_parse_from(cmd.split(" "))?;
+        super::run(dir, options, &[][..])?;
+        Ok(())
+    }
+
+    fn run_command_stdin(cmd: &str, stdin: &str) -> anyhow::Result<TempDir> {
+        let dir = tempfile::tempdir()?;
+        run_command_stdin_in(dir.path(), cmd, stdin)?;
+        Ok(dir)
+    }
+
+    fn run_command_stdin_in(dir: &Path, cmd: &str, stdin: &str) -> anyhow::Result<()> {
+        let options = Options::try_parse_from(cmd.split(" "))?;
+        super::run(dir, options, stdin.as_bytes())?;
+        Ok(())
+    }
+
+    /// Runs `check` as the test `name` alone in a child process, for tests that change
+    /// process-wide state like the umask, which would leak into tests running alongside.
+    #[cfg(unix)]
+    fn in_child_process(
+        name: &str,
+        check: impl FnOnce() -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        const CHILD: &str = "MK_TEST_CHILD_PROCESS";
+        if std::env::var_os(CHILD).is_some() {
+            return check();
+        }
+        let output = std::process::Command::new(std::env::current_exe()?)
+            .args([&format!("tests::{name}"), "--exact"])
+            .env(CHILD, "1")
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // A name that matches no test would pass without checking anything.
+        anyhow::ensure!(
+            output.status.success() && stdout.contains("1 passed"),
+            "{name} failed in its child process:\n{stdout}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn creates_root_file() -> anyhow::Result<()> {
+        let dir = run_command("mk foo.txt")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo.txt"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_root_dir() -> anyhow::Result<()> {
+        let dir = run_command("mk foo")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_nested_file() -> anyhow::Result<()> {
+        let dir = run_command("mk foo/bar/baz.txt")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo"))?.is_dir());
+        assert!(std::fs::metadata(dir.path().join("foo/bar"))?.is_dir());
+        assert!(std::fs::metadata(dir.path().join("foo/bar/baz.txt"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_nested_dir() -> anyhow::Result<()> {
+        let dir = run_command("mk foo/bar/baz")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo"))?.is_dir());
+        assert!(std::fs::metadata(dir.path().join("foo/bar"))?.is_dir());
+        assert!(std::fs::metadata(dir.path().join("foo/bar/baz"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_dot_dir() -> anyhow::Result<()> {
+        let dir = run_command("mk .dir")?;
+
+        assert!(std::fs::metadata(dir.path().join(".dir"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_file_with_dash_f() -> anyhow::Result<()> {
+        let dir = run_command("mk -f dir_like")?;
+
+        assert!(std::fs::metadata(dir.path().join("dir_like"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_dir_with_dash_d() -> anyhow::Result<()> {
+        let dir = run_command("mk -d file_like.txt")?;
+
+        assert!(std::fs::metadata(dir.path().join("file_like.txt"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn appends_to_existing_file() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk --append profile.d/rust.sh", "export A=1\n")?;
+        let options =
+            Options::try_parse_from("mk --append --boilerplate profile.d/rust.sh".split(' '))?;
+        super::run(dir.path(), options, &b"export B=2\n"[..])?;
+
+        let path = dir.path().join("profile.d/rust.sh");
+        assert_eq!(std::fs::read_to_string(&path)?, "export A=1\nexport B=2\n");
+        assert!(run_command_in(dir.path(), "mk --append profile.d").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn writes_lines() -> anyhow::Result<()> {
+        let dir = run_command("mk .env --line FOO=1 --line BAR=2 -f")?;
+        run_command_in(
+            dir.path(),
+            "mk --line-ending crlf --line a --line b out.txt",
+        )?;
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "[*.bat]\nend_of_line = crlf\n",
+        )?;
+        run_command_in(dir.path(), "mk --line @echo --line off run.bat")?;
+
+        let read = |name: &str| std::fs::read_to_string(dir.path().join(name));
+        let newline = if cfg!(windows) { "\r\n" } else { "\n" };
+        assert_eq!(read(".env")?, format!("FOO=1{newline}BAR=2{newline}"));
+        assert_eq!(read("out.txt")?, "a\r\nb\r\n");
+        assert_eq!(read("run.bat")?, "@echo\r\noff\r\n");
+        assert!(run_command_stdin("mk --line a in.txt", "piped").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn reads_stdin_as_told() -> anyhow::Result<()> {
+        let reads = |cmd: &str, is_terminal| {
+            Options::try_parse_from(cmd.split(' ')).map(|o| o.reads_stdin(is_terminal))
+        };
+
+        assert!(reads("mk foo", false)?);
+        assert!(!reads("mk foo", true)?);
+        assert!(reads("mk --stdin foo", true)?);
+        assert!(!reads("mk --no-stdin foo", false)?);
+        assert!(reads("mk --no-stdin --stdin foo", false)?);
+
+        let dir = run_command_stdin("mk logs", "")?;
+        assert!(dir.path().join("logs").is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn writes_contents_while_checksumming() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk --checksum sha256 setup/install.sh", "echo hi\n")?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("setup/install.sh"))?,
+            "echo hi\n"
+        );
+        assert!(run_command_in(dir.path(), "mk --checksum sha256 -d logs").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn verifies_contents_before_keeping_them() -> anyhow::Result<()> {
+        let verify =
+            "--verify sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let dir = run_command_stdin(&format!("mk {verify} downloads/ok.sh"), "abc")?;
+        let options = Options::try_parse_from(format!("mk {verify} downloads/bad.sh").split(' '))?;
+        let err = super::run(dir.path(), options, &b"abd"[..]).unwrap_err();
+
+        assert!(err.to_string().contains("expected ba7816bf"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("downloads/ok.sh"))?,
+            "abc"
+        );
+        assert_eq!(std::fs::read_dir(dir.path().join("downloads"))?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn copies_contents_from_file() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk seeds/base.img", "seed")?;
+        run_command_in(dir.path(), "mk --from seeds/base.img vms/a.img vms/b.img")?;
+
+        for copy in ["vms/a.img", "vms/b.img"] {
+            assert_eq!(std::fs::read_to_string(dir.path().join(copy))?, "seed");
+        }
+        assert!(run_command_in(dir.path(), "mk --from seeds/missing.img vms/c.img").is_err());
+        assert!(!dir.path().join("vms/c.img").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn overwrites_from_file_only_once_copied() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk a.txt", "original")?;
+        std::fs::write(dir.path().join("b.txt"), "copy")?;
+
+        let err = run_command_in(dir.path(), "mk -o --from a.txt a.txt").unwrap_err();
+        assert_eq!(exit::code(&err), exit::INVALID_ARGUMENTS);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt"))?,
+            "original"
+        );
+
+        #[cfg(target_os = "linux")]
+        {
+            // Reading the start of our own memory fails partway into the copy.
+            assert!(run_command_in(dir.path(), "mk -o --from /proc/self/mem a.txt").is_err());
+            assert_eq!(
+                std::fs::read_to_string(dir.path().join("a.txt"))?,
+                "original"
+            );
+        }
+
+        run_command_in(dir.path(), "mk -o --from b.txt a.txt")?;
+        assert_eq!(std::fs::read_to_string(dir.path().join("a.txt"))?, "copy");
+        Ok(())
+    }
+
+    #[test]
+    fn makes_only_if_older_than_reference() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk out/stale.txt", "stale")?;
+        run_command_stdin_in(dir.path(), "mk out/fresh.txt", "fresh")?;
+        let hour = std::time::Duration::from_secs(60 * 60);
+        let now = std::time::SystemTime::now();
+        let set_modified = |name: &str, time| {
+            std::fs::File::options()
+                .write(true)
+                .open(dir.path().join(name))?
+                .set_modified(time)
+        };
+        set_modified("out/stale.txt", now - 2 * hour)?;
+        run_command_stdin_in(dir.path(), "mk src.txt", "source")?;
+        set_modified("src.txt", now - hour)?;
+
+        for name in ["out/stale.txt", "out/fresh.txt", "out/new.txt"] {
+            run_command_stdin_in(
+                dir.path(),
+                &format!("mk --if-newer src.txt {name}"),
+                "built",
+            )?;
+        }
+        run_command_stdin_in(dir.path(), "mk --if-newer @0 out/epoch.txt", "built")?;
+
+        let read = |name: &str| std::fs::read_to_string(dir.path().join(name));
+        assert_eq!(read("out/stale.txt")?, "built");
+        assert_eq!(read("out/fresh.txt")?, "fresh");
+        assert_eq!(read("out/new.txt")?, "built");
+        assert_eq!(read("out/epoch.txt")?, "built");
+        assert!(run_command_in(dir.path(), "mk --if-newer missing.txt out/x.txt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn makes_paths_next_to_file() -> anyhow::Result<()> {
+        let dir = run_command("mk src/deep/parser.rs")?;
+        run_command_in(
+            dir.path(),
+            "mk --next-to src/deep/parser.rs parser_tests.rs fixtures/",
+        )?;
+
+        assert!(dir.path().join("src/deep/parser_tests.rs").is_file());
+        assert!(dir.path().join("src/deep/fixtures").is_dir());
+        assert!(run_command_in(dir.path(), "mk --next-to src/missing.rs other.rs").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn makes_test_companions() -> anyhow::Result<()> {
+        let dir = run_command("mk --companion test src/parser.rs pkg/lexer.go")?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("tests/parser.rs"))?,
+            "#[test]\nfn parser() {}\n"
+        );
+        assert!(
+            std::fs::read_to_string(dir.path().join("pkg/lexer_test.go"))?
+                .contains("func TestLexer(t *testing.T)")
+        );
+        assert!(!dir.path().join("src/parser.rs").exists());
+        assert!(run_command_in(dir.path(), "mk --companion test notes.txt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn names_files_by_content_hash() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk --hash-name assets/{sha256}", "abc")?;
+        let stored = dir
+            .path()
+            .join("assets/ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        assert_eq!(std::fs::read_to_string(&stored)?, "abc");
+        run_command_stdin_in(dir.path(), "mk --hash-name assets/{sha256}", "abc")?;
+        assert!(run_command_stdin_in(dir.path(), "mk --hash-name assets/blob.bin", "abc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_invalid_structured_contents() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk config/app.json", "{\"port\": 80}")?;
+        assert!(dir.path().join("config/app.json").is_file());
+
+        let e = run_command_stdin_in(dir.path(), "mk deploy/app.json", "{\n  \"port\": 80,\n}")
+            .unwrap_err();
+        assert!(format!("{e:#}").contains("line 3, column 1"));
+        assert!(!dir.path().join("deploy").exists());
+
+        run_command_stdin_in(dir.path(), "mk --no-validate broken.toml", "[package")?;
+        assert!(run_command_stdin_in(dir.path(), "mk --validate yaml ci", "a: [b\n").is_err());
+        run_command_in(dir.path(), "mk empty.json")?;
+        Ok(())
+    }
+
+    #[test]
+    fn errors_if_already_exists() -> anyhow::Result<()> {
+        let dir = run_command("mk foo.txt")?;
+
+        assert!(run_command_in(dir.path(), "mk foo.txt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn hints_at_flags_when_existing_kind_differs() -> anyhow::Result<()> {
+        let dir = run_command("mk notes/todo.md build.d/")?;
+        run_command_in(dir.path(), "mk -f notes/plan")?;
+        let err = |cmd| run_command_in(dir.path(), cmd).unwrap_err();
+
+        let message = |cmd| {
+            let e = err(cmd);
+            format!("{e}. {}", hint::for_error(&e).unwrap_or_default())
+        };
+
+        assert_eq!(
+            message("mk -f notes"),
+            "notes already exists as a directory, not a file. Use -o -r to replace it and \
+             everything in it with a file"
+        );
+        assert_eq!(
+            message("mk -f build.d"),
+            "build.d already exists as a directory, not a file. Use -o to replace it with a file"
+        );
+        assert_eq!(
+            message("mk notes/plan"),
+            "notes/plan already exists as a file, not a directory. Use -o to replace it with a \
+             directory, or -f if the existing file is what you meant"
+        );
+        assert_eq!(exit::code(&err("mk notes/plan")), exit::TYPE_CONFLICT);
+        Ok(())
+    }
+
+    #[test]
+    fn overwrites_existing_file() -> anyhow::Result<()> {
+        let dir = run_command("mk foo.txt")?;
+
+        run_command_in(dir.path(), "mk -o foo.txt")?;
+        Ok(())
+    }
+
+    #[test]
+    fn writes_stdin_to_created_file() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk foo.txt", "some contents")?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("foo.txt"))?,
+            "some contents"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_stdin_for_dir() -> anyhow::Result<()> {
+        assert!(run_command_stdin("mk foo", "some contents").is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn marks_file_executable() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = run_command("mk foo.sh")?;
+
+        let file = std::fs::File::open(dir.path().join("foo.sh"))?;
+        assert_eq!(file.metadata()?.permissions().mode() & 0o111, 0o111);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn does_not_make_normal_file_executable() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = run_command("mk foo.txt")?;
+
+        let file = std::fs::File::open(dir.path().join("foo.txt"))?;
+        assert_eq!(file.metadata()?.permissions().mode() & 0o111, 0o000);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn forces_executable() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = run_command("mk -x foo.txt")?;
+
+        let file = std::fs::File::open(dir.path().join("foo.txt"))?;
+        assert_eq!(file.metadata()?.permissions().mode() & 0o111, 0o111);
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_gitkeep_into_created_dirs() -> anyhow::Result<()> {
+        let dir = run_command("mk --gitkeep foo/bar")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo/.gitkeep"))?.is_file());
+        assert!(std::fs::metadata(dir.path().join("foo/bar/.gitkeep"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn writes_gitkeep_into_created_parents_of_file() -> anyhow::Result<()> {
+        let dir = run_command("mk --gitkeep foo/bar.txt")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo/.gitkeep"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_write_gitkeep_into_existing_dirs() -> anyhow::Result<()> {
+        let dir = run_command("mk foo")?;
+        run_command_in(dir.path(), "mk --gitkeep foo/bar")?;
+
+        assert!(!dir.path().join("foo/.gitkeep").exists());
+        assert!(dir.path().join("foo/bar/.gitkeep").exists());
+        Ok(())
+    }
+
+    fn git(dir: &Path, args: &str) -> anyhow::Result<String> {
+        let output = std::process::Command::new("git")
+            .args(args.split(" "))
+            .current_dir(dir)
+            .output()?;
+        anyhow::ensure!(output.status.success(), "git {args} failed");
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    #[test]
+    fn stages_created_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        git(dir.path(), "init -q")?;
+
+        run_command_in(dir.path(), "mk --git-add foo/bar.txt")?;
+
+        assert_eq!(
+            git(dir.path(), "diff --cached --name-only")?,
+            "foo/bar.txt\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stages_gitkeeps_of_created_dirs() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        git(dir.path(), "init -q")?;
+
+        run_command_in(dir.path(), "mk --gitkeep --git-add foo/bar")?;
+
+        assert_eq!(
+            git(dir.path(), "diff --cached --name-only")?,
+            "foo/.gitkeep\nfoo/bar/.gitkeep\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors_staging_outside_work_tree() -> anyhow::Result<()> {
+        assert!(run_command("mk --git-add foo.txt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_missing_zoxide() -> anyhow::Result<()> {
+        let dir = run_command("mk --zoxide project")?;
+
+        assert!(dir.path().join("project").is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn initializes_git_repository() -> anyhow::Result<()> {
+        let dir = run_command("mk --git-init project")?;
+
+        assert!(std::fs::metadata(dir.path().join("project/.git"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn detects_git_ignored_entries() -> anyhow::Result<()> {
+        let dir = run_command("mk --git-init project")?;
+        std::fs::write(dir.path().join("project/.gitignore"), "*.log\nbuild/\n")?;
+        run_command_in(
+            dir.path(),
+            "mk project/debug.log project/build project/main.rs",
+        )?;
+
+        let project = dir.path().join("project");
+        let paths = [
+            project.join("debug.log"),
+            project.join("build"),
+            project.join("main.rs"),
+            dir.path().join("outside.log"),
+        ];
+        assert_eq!(git::ignored(&paths), paths[..2]);
+        Ok(())
+    }
+
+    #[test]
+    fn errors_initializing_git_in_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(run_command_in(dir.path(), "mk --git-init foo.txt").is_err());
+        assert!(!dir.path().join("foo.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_path_from_git_root() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        git(dir.path(), "init -q")?;
+        std::fs::create_dir_all(dir.path().join("deep/inside"))?;
+
+        run_command_in(&dir.path().join("deep/inside"), "mk --git-root docs/foo.md")?;
+
+        assert!(std::fs::metadata(dir.path().join("docs/foo.md"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_resolving_git_root_outside_repository() -> anyhow::Result<()> {
+        assert!(run_command("mk --git-root foo.txt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_relative_to_dash_c() -> anyhow::Result<()> {
+        let dir = run_command("mk app")?;
+
+        run_command_in(dir.path(), "mk -C app releases/2024-06-01/")?;
+
+        assert!(std::fs::metadata(dir.path().join("app/releases/2024-06-01"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_missing_dash_c_dir() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(run_command_in(dir.path(), "mk -C app foo.txt").is_err());
+        assert!(!dir.path().join("app").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_multiple_paths() -> anyhow::Result<()> {
+        let dir = run_command("mk foo.txt bar")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo.txt"))?.is_file());
+        assert!(std::fs::metadata(dir.path().join("bar"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_stdin_for_multiple_paths() -> anyhow::Result<()> {
+        assert!(run_command_stdin("mk foo.txt bar.txt", "some contents").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_paths_from_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("list"), "foo.txt\n\nbar/baz\n")?;
+
+        run_command_in(dir.path(), "mk --files-from list")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo.txt"))?.is_file());
+        assert!(std::fs::metadata(dir.path().join("bar/baz"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_paths_from_stdin() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk --files-from -", "foo.txt\nbar\n")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo.txt"))?.is_file());
+        assert!(std::fs::metadata(dir.path().join("bar"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_nul_separated_paths() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk --files-from - --null", "with space.txt\0bar\0")?;
+
+        assert!(std::fs::metadata(dir.path().join("with space.txt"))?.is_file());
+        assert!(std::fs::metadata(dir.path().join("bar"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_going_after_failure() -> anyhow::Result<()> {
+        let dir = run_command("mk bar.txt")?;
+
+        assert!(run_command_in(dir.path(), "mk -k foo.txt bar.txt baz.txt").is_err());
+        assert!(dir.path().join("foo.txt").exists());
+        assert!(dir.path().join("baz.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn stops_at_first_failure() -> anyhow::Result<()> {
+        let dir = run_command("mk bar.txt")?;
+
+        assert!(run_command_in(dir.path(), "mk foo.txt bar.txt baz.txt").is_err());
+        assert!(dir.path().join("foo.txt").exists());
+        assert!(!dir.path().join("baz.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn rolls_back_atomic_batch() -> anyhow::Result<()> {
+        let dir = run_command("mk existing/bar.txt")?;
+
+        assert!(
+            run_command_in(
+                dir.path(),
+                "mk --atomic foo/a.txt existing/b.txt existing/bar.txt"
+            )
+            .is_err()
+        );
+        assert!(!dir.path().join("foo").exists());
+        assert!(!dir.path().join("existing/b.txt").exists());
+        assert!(dir.path().join("existing/bar.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn rolls_back_parents_of_failed_entry() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(run_command_in(dir.path(), "mk --atomic --git-add foo/bar.txt").is_err());
+        assert!(!dir.path().join("foo").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn undoes_last_invocation() -> anyhow::Result<()> {
+        let dir = run_command("mk existing")?;
+        let journal = dir.path().join("journal");
+
+        run_command_in(
+            dir.path(),
+            &format!(
+                "mk --journal {} --gitkeep existing/foo/bar.txt baz",
+                journal.display()
+            ),
+        )?;
+        run_command_in(
+            dir.path(),
+            &format!("mk --journal {} undo", journal.display()),
+        )?;
+
+        assert!(dir.path().join("existing").exists());
+        assert!(!dir.path().join("existing/foo").exists());
+        assert!(!dir.path().join("baz").exists());
+        assert!(!journal.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn clears_journal_when_nothing_is_made() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let journal = dir.path().join("journal");
+        let cmd = |args: &str| format!("mk --journal {} {args}", journal.display());
+
+        run_command_in(dir.path(), &cmd("foo.txt"))?;
+        assert!(run_command_in(dir.path(), &cmd("foo.txt")).is_err());
+
+        assert!(!journal.exists());
+        assert!(run_command_in(dir.path(), &cmd("undo")).is_err());
+        assert!(dir.path().join("foo.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn undoes_paths_that_are_not_utf8() -> anyhow::Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir()?;
+        let journal = dir.path().join("journal");
+        let path = dir
+            .path()
+            .join(std::ffi::OsStr::from_bytes(b"caf\xe9\nname"));
+        std::fs::write(&path, "")?;
+
+        write_journal(&journal, std::slice::from_ref(&path))?;
+        undo(&journal)?;
+
+        assert!(!path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn undo_keeps_directories_with_new_contents() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let journal = dir.path().join("journal");
+
+        run_command_in(
+            dir.path(),
+            &format!("mk --journal {} foo", journal.display()),
+        )?;
+        std::fs::write(dir.path().join("foo/unrelated.txt"), "")?;
+        run_command_in(
+            dir.path(),
+            &format!("mk --journal {} undo", journal.display()),
+        )?;
+
+        assert!(dir.path().join("foo/unrelated.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn makes_kind_named_by_subcommand() -> anyhow::Result<()> {
+        let dir = run_command("mk dir build/out.d2 v1")?;
+        run_command_in(dir.path(), "mk file Makefile.d/rules")?;
+
+        assert!(dir.path().join("build/out.d2").is_dir());
+        assert!(dir.path().join("v1").is_dir());
+        assert!(dir.path().join("Makefile.d/rules").is_file());
+        assert!(run_command("mk dir").is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn makes_and_undoes_links() -> anyhow::Result<()> {
+        let dir = run_command("mk shared/config.toml")?;
+        let journal = dir.path().join("journal");
+
+        run_command_in(
+            dir.path(),
+            &format!(
+                "mk --journal {} link ../../shared apps/web/shared",
+                journal.display()
+            ),
+        )?;
+        run_command_in(dir.path(), "mk link --hard shared/config.toml hard.toml")?;
+
+        assert!(dir.path().join("apps/web/shared/config.toml").is_file());
+        assert!(dir.path().join("hard.toml").is_file());
+        assert!(run_command_in(dir.path(), "mk link shared apps/web/shared").is_err());
+        run_command_in(
+            dir.path(),
+            &format!("mk --journal {} undo", journal.display()),
+        )?;
+        assert!(!dir.path().join("apps").exists());
+        assert!(dir.path().join("shared/config.toml").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn makes_tree_from_outline() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join("outline"),
+            "app\n  src\n    main.rs\n  README.md\n",
+        )?;
+
+        run_command_in(dir.path(), "mk tree outline")?;
+        let options = Options::try_parse_from("mk --gitkeep tree".split(" "))?;
+        super::run(dir.path(), options, "bin\n  run.py\n".as_bytes())?;
+
+        assert!(dir.path().join("app/src/main.rs").is_file());
+        assert!(dir.path().join("app/README.md").is_file());
+        assert!(dir.path().join("bin/run.py").is_file());
+        assert!(dir.path().join("bin/.gitkeep").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn runs_batch_as_one_invocation() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let journal = dir.path().join("journal");
+        let batch = "# scaffold\nsrc/main.rs 'docs/read me.md'\n\n-d assets.d\ndir logs\n";
+
+        let options = Options::try_parse_from(
+            format!("mk --batch --journal {}", journal.display()).split(" "),
+        )?;
+        super::run(dir.path(), options, batch.as_bytes())?;
+
+        assert!(dir.path().join("src/main.rs").is_file());
+        assert!(dir.path().join("docs/read me.md").is_file());
+        assert!(dir.path().join("assets.d").is_dir());
+        assert!(dir.path().join("logs").is_dir());
+
+        run_command_in(
+            dir.path(),
+            &format!("mk --journal {} undo", journal.display()),
+        )?;
+        for path in ["src", "docs", "assets.d", "logs"] {
+            assert!(!dir.path().join(path).exists());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rolls_back_whole_batch() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        let options = Options::try_parse_from("mk --batch --atomic".split(" "))?;
+        let err = super::run(dir.path(), options, "a/b.txt\na/b.txt\n".as_bytes()).unwrap_err();
+
+        assert!(format!("{err:#}").starts_with("Line 2: a/b.txt"));
+        assert!(!dir.path().join("a").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn applies_line_flags_in_batch() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let batch = "--atomic a/b.txt existing.txt\nexisting.txt\n--summary c.txt\n";
+
+        let options = Options::try_parse_from("mk --batch --keep-going".split(" "))?;
+        std::fs::write(dir.path().join("existing.txt"), "")?;
+        assert!(super::run(dir.path(), options, batch.as_bytes()).is_err());
+
+        assert!(!dir.path().join("a").exists());
+        assert!(!dir.path().join("c.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn applies_line_umask_in_batch() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        in_child_process("applies_line_umask_in_batch", || {
+            let dir = run_command_stdin("mk --batch", "--umask 027 shared\n")?;
+
+            let mode = dir.path().join("shared").metadata()?.permissions().mode() & 0o777;
+            assert_eq!(mode, 0o750);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn counts_written_files() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk docs/a.txt", "hello")?;
+        let written = std::rc::Rc::default();
+        let mut created = Vec::new();
+        for (cmd, stdin) in [
+            ("mk --append docs/a.txt", "!!"),
+            ("mk --size 2G --gitkeep sparse/big.bin", ""),
+        ] {
+            let mut options = Options::try_parse_from(cmd.split(" "))?;
+            options.written = std::rc::Rc::clone(&written);
+            super::make(dir.path(), options, stdin.as_bytes(), &mut created)?;
+        }
+
+        let summary = summary::Summary::new(&created, written.get(), 0, Default::default());
+        assert_eq!(
+            (summary.directories, summary.files, summary.bytes),
+            (1, 2, 2)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_nothing_to_undo() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let journal = dir.path().join("journal");
+
+        assert!(
+            run_command_in(
+                dir.path(),
+                &format!("mk --journal {} undo", journal.display())
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fills_template_variables() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join("post.tmpl"),
+            "# {{name}}\nby {{author}} on {{date:today}}\n",
+        )?;
+        std::fs::write(dir.path().join(".mk.toml"), "vars = { author = \"Me\" }")?;
+
+        run_command_in(dir.path(), "mk --template post.tmpl posts/hello.md")?;
+        run_command_in(
+            dir.path(),
+            "mk --template post.tmpl --var author=You --var date=monday posts/bye.md",
+        )?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("posts/hello.md"))?,
+            "# hello\nby Me on today\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("posts/bye.md"))?,
+            "# bye\nby You on monday\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fills_template_from_snippet_library() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("config.toml");
+        std::fs::write(&config, "snippets = \"library\"")?;
+        std::fs::write(dir.path().join("draft.md"), "# {{name}}\n")?;
+
+        run_command_in(
+            dir.path(),
+            &format!(
+                "mk --config {} snippets add draft.md --name post",
+                config.display()
+            ),
+        )?;
+        run_command_in(
+            dir.path(),
+            &format!("mk --config {} --template post hello.md", config.display()),
+        )?;
+
+        assert!(dir.path().join("library/post").is_file());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("hello.md"))?,
+            "# hello\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn formats_templates_for_editorconfig() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("main.tmpl"), "fn main() {\n\ttodo!()\n}")?;
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n[*.rs]\nindent_style = space\nindent_size = 4\ninsert_final_newline = true\n",
+        )?;
+
+        run_command_in(dir.path(), "mk --template main.tmpl src/main.rs")?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("src/main.rs"))?,
+            "fn main() {\n    todo!()\n}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn makes_files_by_type() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("mod.tmpl"), "//! {{name}}\n")?;
+        std::fs::write(
+            dir.path().join(".mk.toml"),
+            "[types.rust]\ntemplate = \"mod.tmpl\"",
+        )?;
+
+        run_command_in(dir.path(), "mk --type rust src/parser")?;
+        run_command_in(dir.path(), "mk --type sh scripts/deploy")?;
+        let options = Options::try_parse_from("mk --type bash piped".split(" "))?;
+        super::run(dir.path(), options, "echo hi\n".as_bytes())?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("src/parser.rs"))?,
+            "//! parser\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("scripts/deploy.sh"))?,
+            "#!/usr/bin/env bash\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("piped.sh"))?,
+            "echo hi\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = dir
+                .path()
+                .join("scripts/deploy.sh")
+                .metadata()?
+                .permissions()
+                .mode();
+            assert_ne!(mode & 0o111, 0);
+        }
+        assert!(run_command_in(dir.path(), "mk --type cobol main").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn starts_files_with_boilerplate() -> anyhow::Result<()> {
+        let dir = run_command("mk --boilerplate src/main.rs src/lib.rs")?;
+        std::fs::write(dir.path().join(".mk.toml"), "boilerplate = true")?;
+        run_command_in(dir.path(), "mk --type bash deploy")?;
+        run_command_in(dir.path(), "mk --no-boilerplate plain.py")?;
+
+        let read = |name: &str| std::fs::read_to_string(dir.path().join(name));
+        assert_eq!(read("src/main.rs")?, "fn main() {}\n");
+        assert_eq!(read("src/lib.rs")?, "");
+        assert_eq!(
+            read("deploy.sh")?,
+            "#!/usr/bin/env bash\nset -euo pipefail\n\n"
+        );
+        assert_eq!(read("plain.py")?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn starts_markdown_with_frontmatter() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join(".mk.toml"),
+            "[frontmatter]\ndraft = true\ndate = \"someday\"",
+        )?;
+
+        run_command_in(dir.path(), "mk --frontmatter posts/hello-world.md")?;
+        run_command_in(dir.path(), "mk --frontmatter=toml posts/second_post.md")?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("posts/hello-world.md"))?,
+            "---\ntitle: \"Hello World\"\ndate: \"someday\"\ndraft: true\n---\n\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("posts/second_post.md"))?,
+            "+++\ntitle = \"Second Post\"\ndate = \"someday\"\ndraft = true\n+++\n\n"
+        );
+        assert!(run_command_in(dir.path(), "mk --frontmatter notes.txt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_missing_template_variable() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("post.tmpl"), "by {{author}}")?;
+
+        let err =
+            run_command_in(dir.path(), "mk --no-input --template post.tmpl post.md").unwrap_err();
+
+        assert!(err.to_string().contains("--var author="));
+        assert!(!dir.path().join("post.md").exists());
+        Ok(())
+    }
+
+    fn run_command_with_config(config: &str, cmd: &str) -> anyhow::Result<TempDir> {
+        let dir = tempfile::tempdir()?;
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, config)?;
+        run_command_in(
+            dir.path(),
+            &format!("{cmd} --config {}", config_path.display()),
+        )?;
+        Ok(dir)
+    }
+
+    #[test]
+    fn uses_configured_kind() -> anyhow::Result<()> {
+        let dir = run_command_with_config("kind = \"file\"", "mk foo")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn flags_override_configured_kind() -> anyhow::Result<()> {
+        let dir = run_command_with_config("kind = \"file\"", "mk -d foo")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn uses_configured_dotfile_kind() -> anyhow::Result<()> {
+        let dir = run_command_with_config("dotfiles = \"file\"", "mk .env")?;
+
+        assert!(std::fs::metadata(dir.path().join(".env"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn uses_configured_gitkeep() -> anyhow::Result<()> {
+        let dir = run_command_with_config("gitkeep = true", "mk foo")?;
+
+        assert!(dir.path().join("foo/.gitkeep").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn flags_override_configured_gitkeep() -> anyhow::Result<()> {
+        let dir = run_command_with_config("gitkeep = true", "mk --no-gitkeep foo")?;
+
+        assert!(!dir.path().join("foo/.gitkeep").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_invalid_config() -> anyhow::Result<()> {
+        assert!(run_command_with_config("unknown = 1", "mk foo").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn uses_project_config_from_ancestor() -> anyhow::Result<()> {
+        let dir = run_command("mk project/src")?;
+        std::fs::write(dir.path().join("project/.mk.toml"), "kind = \"file\"")?;
+
+        run_command_in(&dir.path().join("project/src"), "mk foo")?;
+
+        assert!(std::fs::metadata(dir.path().join("project/src/foo"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn project_config_overrides_user_config() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join("config.toml"),
+            "kind = \"file\"\ngitkeep = true",
+        )?;
+        std::fs::write(dir.path().join(".mk.toml"), "kind = \"directory\"")?;
+
+        run_command_in(
+            dir.path(),
+            &format!(
+                "mk foo.txt --config {}",
+                dir.path().join("config.toml").display()
+            ),
+        )?;
+
+        assert!(std::fs::metadata(dir.path().join("foo.txt"))?.is_dir());
+        assert!(dir.path().join("foo.txt/.gitkeep").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn prepends_env_options() -> anyhow::Result<()> {
+        let args = with_env_options(
+            ["mk", "-d", "foo.txt"].map(Into::into),
+            Some(" --gitkeep  -o --journal 'my journal' ".to_string()),
+        )?;
+
+        assert_eq!(
+            args,
+            [
+                "mk",
+                "--gitkeep",
+                "-o",
+                "--journal",
+                "my journal",
+                "-d",
+                "foo.txt"
+            ]
+        );
+        assert!(with_env_options(["mk"].map(Into::into), Some("'unclosed".to_string())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn passes_args_through_without_env_options() -> anyhow::Result<()> {
+        let args = with_env_options(["mk", "foo.txt"].map(Into::into), None)?;
+
+        assert_eq!(args, ["mk", "foo.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn applies_profile() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = run_command_with_config(
+            "[profile.script]\nkind = \"file\"\nexecutable = true",
+            "mk --profile script deploy",
+        )?;
+
+        let file = std::fs::File::open(dir.path().join("deploy"))?;
+        assert_eq!(file.metadata()?.permissions().mode() & 0o111, 0o111);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn applies_script_profile() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = run_command_with_config(
+            "[profile]\nscript = { kind = \"file\", executable = true, shebang = \"bash\", mode = \"755\" }",
+            "mk --profile script bin/deploy",
+        )?;
+
+        let path = dir.path().join("bin/deploy");
+        assert_eq!(std::fs::read_to_string(&path)?, "#!/usr/bin/env bash\n");
+        assert_eq!(
+            std::fs::metadata(&path)?.permissions().mode() & 0o7777,
+            0o755
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn adds_shebangs_by_pattern() -> anyhow::Result<()> {
+        let dir = run_command_with_config(
+            "shebangs = { \"**/*.sh\" = \"bash\" }",
+            "mk build/ docs notes.txt scripts/deploy.sh",
+        )?;
+
+        assert!(dir.path().join("build").is_dir());
+        assert!(dir.path().join("docs").is_dir());
+        assert_eq!(std::fs::read_to_string(dir.path().join("notes.txt"))?, "");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("scripts/deploy.sh"))?,
+            "#!/usr/bin/env bash\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_shebang_outside_profiles() {
+        assert!(run_command_with_config("shebang = \"bash\"", "mk docs").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn runs_hooks_with_resolved_paths() -> anyhow::Result<()> {
+        let dir = run_command_with_config(
+            "[hooks.\"adr-*\"]\npre = \"echo pre {path} >> log\"\npost = \"echo post {path} >> log\"",
+            "mk adr-{n}.md",
+        )?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("log"))?,
+            "pre adr-1.md\npost adr-1.md\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn runs_project_hooks_only_when_trusted() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join(".mk.toml"),
+            "[hooks.\"*.md\"]\npost = \"touch hooked\"",
+        )?;
+
+        run_command_in(dir.path(), "mk a.md")?;
+        assert!(!dir.path().join("hooked").exists());
+        run_command_in(dir.path(), "mk --trust-hooks b.md")?;
+        assert!(dir.path().join("hooked").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn exits_with_failing_hooks() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join(".mk.toml"),
+            "[hooks.\"*.md\"]\npre = \"exit 1\"",
+        )?;
+
+        let err = run_command_in(dir.path(), "mk --trust-hooks a.md").unwrap_err();
+        assert_eq!(exit::code(&err), exit::HOOK_FAILED);
+        assert!(!dir.path().join("a.md").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_unknown_profile() -> anyhow::Result<()> {
+        assert!(run_command_with_config("", "mk --profile missing foo").is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn is_executable(path: &Path) -> anyhow::Result<bool> {
+        use std::os::unix::fs::PermissionsExt;
+
+        Ok(std::fs::metadata(path)?.permissions().mode() & 0o111 == 0o111)
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn uses_configured_executable_extensions() -> anyhow::Result<()> {
+        let config = "executable_extensions = { add = [\"xsh\"], remove = [\"py\"] }";
+        let dir = run_command_with_config(config, "mk foo.xsh bar.py")?;
+
+        assert!(is_executable(&dir.path().join("foo.xsh"))?);
+        assert!(!is_executable(&dir.path().join("bar.py"))?);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn skips_auto_exec() -> anyhow::Result<()> {
+        let dir = run_command("mk --no-auto-exec foo.sh")?;
+
+        assert!(!is_executable(&dir.path().join("foo.sh"))?);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn overrides_configured_executable() -> anyhow::Result<()> {
+        let dir = run_command_with_config(
+            "executable = true",
+            "mk --no-executable --no-auto-exec foo.txt bar.sh",
+        )?;
+
+        assert!(!is_executable(&dir.path().join("foo.txt"))?);
+        assert!(!is_executable(&dir.path().join("bar.sh"))?);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn forces_executable_without_auto_exec() -> anyhow::Result<()> {
+        let dir = run_command("mk --no-auto-exec -x foo.sh")?;
+
+        assert!(is_executable(&dir.path().join("foo.sh"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn infers_directory_extensions() -> anyhow::Result<()> {
+        let dir = run_command("mk rollout.d v1.2 backup.2024")?;
+
+        assert!(std::fs::metadata(dir.path().join("rollout.d"))?.is_dir());
+        assert!(std::fs::metadata(dir.path().join("v1.2"))?.is_dir());
+        assert!(std::fs::metadata(dir.path().join("backup.2024"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn uses_configured_directory_extensions() -> anyhow::Result<()> {
+        let config = "directory_extensions = { add = [\"bak*\"], remove = [\"d\"] }";
+        let dir = run_command_with_config(config, "mk old.bak2 rollout.d")?;
+
+        assert!(std::fs::metadata(dir.path().join("old.bak2"))?.is_dir());
+        assert!(std::fs::metadata(dir.path().join("rollout.d"))?.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_dir_with_trailing_separator() -> anyhow::Result<()> {
+        let dir = run_command("mk foo/bar.txt/")?;
+
+        assert!(std::fs::metadata(dir.path().join("foo/bar.txt"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn trailing_separator_overrides_configured_mode() -> anyhow::Result<()> {
+        let dir = run_command_with_config("kind = \"file\"", "mk build/")?;
+
+        assert!(std::fs::metadata(dir.path().join("build"))?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_forcing_file_with_trailing_separator() -> anyhow::Result<()> {
+        assert!(run_command("mk -f foo.txt/").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn numbers_entries_sequentially() -> anyhow::Result<()> {
+        let dir = run_command("mk adr/{n:04}-first.md adr/{n:04}-second.md")?;
+
+        assert!(dir.path().join("adr/0001-first.md").exists());
+        assert!(dir.path().join("adr/0002-second.md").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn slugifies_after_placeholders() -> anyhow::Result<()> {
+        let dir = run_command("mk --slug posts/{n:02}-Hello_World!.md")?;
+
+        assert!(dir.path().join("posts/01-hello-world.md").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_names_with_colons_local() -> anyhow::Result<()> {
+        let dir = run_command("mk meeting:notes.md")?;
+
+        assert!(dir.path().join("meeting:notes.md").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_portable_reserved_names() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(run_command_in(dir.path(), "mk --portable-names docs/aux.txt").is_err());
+        assert!(!dir.path().join("docs").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn forces_portable_reserved_names() -> anyhow::Result<()> {
+        let dir = run_command("mk --portable-names --force docs/aux.txt")?;
+
+        assert!(dir.path().join("docs/aux.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn normalizes_created_names() -> anyhow::Result<()> {
+        let dir = run_command("mk --normalize nfc cafe\u{301}.md")?;
+
+        assert!(dir.path().join("caf\u{e9}.md").exists());
+        assert!(!dir.path().join("cafe\u{301}.md").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_beyond_max_depth() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(run_command_in(dir.path(), "mk --max-depth 2 a/b/c").is_err());
+        assert!(!dir.path().join("a").exists());
+        run_command_in(dir.path(), "mk --max-depth 2 a/b")?;
+        Ok(())
+    }
+
+    #[test]
+    fn restricts_escaping_paths() -> anyhow::Result<()> {
+        let dir = run_command("mk root/inner")?;
+        let root = dir.path().join("root");
+
+        assert!(run_command_in(&root, "mk --restrict ../escaped.txt").is_err());
+        assert!(run_command_in(&root, "mk --restrict inner/../../escaped.txt").is_err());
+        assert!(
+            run_command_in(
+                &root,
+                &format!("mk --restrict {}", dir.path().join("abs.txt").display())
+            )
+            .is_err()
+        );
+        assert!(!dir.path().join("escaped.txt").exists());
+
+        run_command_in(&root, "mk --restrict inner/../fine.txt")?;
+        assert!(root.join("fine.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn restricts_paths_through_symlinks() -> anyhow::Result<()> {
+        let dir = run_command("mk root outside")?;
+        let root = dir.path().join("root");
+        std::os::unix::fs::symlink(dir.path().join("outside"), root.join("link"))?;
+
+        assert!(run_command_in(&root, "mk --restrict link/escaped.txt").is_err());
+        assert!(!dir.path().join("outside/escaped.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_missing_parents() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(run_command_in(dir.path(), "mk --no-parents srk/foo.rs").is_err());
+        assert!(run_command_in(dir.path(), "mk --no-parents srk/foo").is_err());
+        assert!(!dir.path().join("srk").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn creates_with_existing_parents() -> anyhow::Result<()> {
+        let dir = run_command("mk src")?;
+
+        run_command_in(dir.path(), "mk --no-parents src/foo.rs src/bar")?;
+        assert!(dir.path().join("src/foo.rs").is_file());
+        assert!(dir.path().join("src/bar").is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_naming_file_parent() -> anyhow::Result<()> {
+        let dir = run_command("mk foo.txt")?;
+
+        let err = run_command_in(dir.path(), "mk foo.txt/bar/baz.txt").unwrap_err();
+        assert!(err.to_string().contains("foo.txt is a file"));
+        Ok(())
+    }
+
+    #[test]
+    fn overwrites_file_parent() -> anyhow::Result<()> {
+        let dir = run_command("mk foo.txt")?;
+
+        run_command_in(dir.path(), "mk --overwrite-parents foo.txt/bar.txt")?;
+        assert!(dir.path().join("foo.txt").is_dir());
+        assert!(dir.path().join("foo.txt/bar.txt").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn overwrites_file_with_dir() -> anyhow::Result<()> {
+        let dir = run_command("mk -f foo")?;
+
+        run_command_in(dir.path(), "mk -o -d foo")?;
+        assert!(dir.path().join("foo").is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn overwrites_empty_dir_with_file() -> anyhow::Result<()> {
+        let dir = run_command("mk -d foo.txt")?;
+
+        run_command_in(dir.path(), "mk -o foo.txt")?;
+        assert!(dir.path().join("foo.txt").is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn overwrites_non_empty_dir_only_recursively() -> anyhow::Result<()> {
+        let dir = run_command("mk -d foo.txt/bar.txt")?;
+
+        assert!(run_command_in(dir.path(), "mk -o foo.txt").is_err());
+        assert!(dir.path().join("foo.txt/bar.txt").exists());
+
+        run_command_in(dir.path(), "mk -o -r foo.txt")?;
+        assert!(dir.path().join("foo.txt").is_file());
+        Ok(())
+    }
+
+    struct FailingReader;
+
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+        }
+    }
+
+    #[test]
+    fn keeps_original_when_overwrite_fails() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk foo.txt", "original")?;
+        let options = Options::try_parse_from("mk -o foo.txt".split(" "))?;
+
+        assert!(super::run(dir.path(), options, FailingReader).is_err());
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("foo.txt"))?,
+            "original"
+        );
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn overwrites_contents() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk foo.txt", "original")?;
+        let options = Options::try_parse_from("mk -o foo.txt".split(" "))?;
+
+        super::run(dir.path(), options, "replaced".as_bytes())?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("foo.txt"))?,
+            "replaced"
+        );
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn keeps_permissions_when_overwriting() -> anyhow::Result<()> {
+        let dir = run_command("mk foo.sh")?;
+
+        run_command_in(dir.path(), "mk -o --no-auto-exec foo.sh")?;
+        assert!(is_executable(&dir.path().join("foo.sh"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn creates_file_of_size() -> anyhow::Result<()> {
+        let dir = run_command("mk --size 2M disk")?;
+
+        let metadata = std::fs::metadata(dir.path().join("disk"))?;
+        assert!(metadata.is_file());
+        assert_eq!(metadata.len(), 2 * 1024 * 1024);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn creates_sparse_file() -> anyhow::Result<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = run_command("mk --size 1G disk.img")?;
+
+        let metadata = std::fs::metadata(dir.path().join("disk.img"))?;
+        assert_eq!(metadata.len(), 1024 * 1024 * 1024);
+        assert!(metadata.blocks() * 512 < metadata.len());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_size_for_dir() -> anyhow::Result<()> {
+        assert!(run_command("mk --size 1K -d disk").is_err());
+        assert!(run_command("mk --size 1K disk/").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_size_and_stdin() -> anyhow::Result<()> {
+        assert!(run_command_stdin("mk --size 1K disk.img", "data").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn writes_stdin_through_small_buffer() -> anyhow::Result<()> {
+        let contents = "some contents".repeat(100);
+        let dir = run_command_stdin("mk --buffer-size 7 foo.txt", &contents)?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("foo.txt"))?,
+            contents
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn accepts_stdin_up_to_max_bytes() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk --max-bytes 4 foo.txt", "four")?;
+
+        assert_eq!(std::fs::read_to_string(dir.path().join("foo.txt"))?, "four");
+        Ok(())
+    }
+
+    #[test]
+    fn removes_file_beyond_max_bytes() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let options = Options::try_parse_from("mk --max-bytes 4 foo.txt".split(" "))?;
+
+        let err = super::run(dir.path(), options, "y\n".repeat(100).as_bytes()).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<mk::Error>(),
+            Some(mk::Error::ContentsTooLong(4))
+        ));
+        assert!(!dir.path().join("foo.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn caps_validated_contents_at_max_bytes() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let options = Options::try_parse_from("mk --max-bytes 4 foo.json".split(" "))?;
+
+        // Endless, so reading all of it before the limit applied would never finish.
+        let err = super::run(dir.path(), options, std::io::repeat(b' ')).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<mk::Error>(),
+            Some(mk::Error::ContentsTooLong(4))
+        ));
+        assert!(!dir.path().join("foo.json").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_original_beyond_max_bytes() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk foo.txt", "original")?;
+        let options = Options::try_parse_from("mk -o --max-bytes 4 foo.txt".split(" "))?;
+
+        assert!(super::run(dir.path(), options, "too long".as_bytes()).is_err());
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("foo.txt"))?,
+            "original"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tees_stdin() -> anyhow::Result<()> {
+        let mut copy = Vec::new();
+        let mut tee = Tee {
+            reader: "some contents".as_bytes(),
+            copy: Some(&mut copy),
+        };
+
+        let mut read = String::new();
+        std::io::Read::read_to_string(&mut tee, &mut read)?;
+
+        assert_eq!(read, "some contents");
+        assert_eq!(copy, b"some contents");
+        Ok(())
+    }
+
+    #[test]
+    fn writes_file_when_teeing() -> anyhow::Result<()> {
+        let dir = run_command_stdin("mk --tee build/manifest.json", "{}")?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("build/manifest.json"))?,
+            "{}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn splits_stdin_into_files() -> anyhow::Result<()> {
+        let dir = run_command_stdin(
+            "mk --split-on --- out",
+            "--- Makefile\nall:\n--- src/main.rs\nfn main() {}\n",
+        )?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("out/Makefile"))?,
+            "all:\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("out/src/main.rs"))?,
+            "fn main() {}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors_splitting_outside_target() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let options = Options::try_parse_from("mk --split-on --- out".split(" "))?;
+
+        assert!(super::run(dir.path(), options, "--- ../escaped.txt\n".as_bytes()).is_err());
+        assert!(!dir.path().join("escaped.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn opens_created_file_in_editor() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let editor = dir.path().join("editor");
+        std::fs::write(&editor, "#!/bin/sh\necho edited > \"$1\"\n")?;
+        std::fs::set_permissions(&editor, std::fs::Permissions::from_mode(0o755))?;
+        let config = dir.path().join("config.toml");
+        std::fs::write(&config, format!("editor = \"{}\"", editor.display()))?;
+
+        run_command_in(
+            dir.path(),
+            &format!("mk notes/idea.md -e --config {}", config.display()),
+        )?;
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes/idea.md"))?,
+            "edited\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_to_edit_directories() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(run_command_in(dir.path(), "mk -e build/").is_err());
+        assert!(run_command_in(dir.path(), "mk -e -d build").is_err());
+        assert!(!dir.path().join("build").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn opens_daily_note() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let editor = dir.path().join("editor");
+        std::fs::write(&editor, "#!/bin/sh\necho opened >> \"$1\"\n")?;
+        std::fs::set_permissions(&editor, std::fs::Permissions::from_mode(0o755))?;
+        std::fs::write(
+            dir.path().join(".mk.toml"),
+            format!(
+                "editor = \"{}\"\ndaily = \"journal/%Y/%m/%d-log.md\"",
+                editor.display()
+            ),
+        )?;
+
+        run_command_in(dir.path(), "mk --daily")?;
+        run_command_in(dir.path(), "mk --daily")?;
+
+        let today = chrono::Local::now();
+        let note = dir
+            .path()
+            .join(today.format("journal/%Y/%m/%d-log.md").to_string());
+        assert_eq!(
+            std::fs::read_to_string(note)?,
+            format!(
+                "---\ntitle: \"{} Log\"\ndate: {}\n---\n\nopened\nopened\n",
+                today.format("%d"),
+                today.format("%Y-%m-%d")
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sets_mode_and_special_bits() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = run_command("mk --mode 2770 shared")?;
+        run_command_in(dir.path(), "mk --sticky tmp")?;
+        run_command_in(dir.path(), "mk --mode 600 -x secret.sh")?;
+
+        let mode = |name: &str| -> anyhow::Result<u32> {
+            Ok(dir.path().join(name).metadata()?.permissions().mode() & 0o7777)
+        };
+        assert_eq!(mode("shared")?, 0o2770);
+        assert_eq!(mode("tmp")? & 0o1000, 0o1000);
+        assert_eq!(mode("secret.sh")?, 0o600);
+        assert!(run_command_in(dir.path(), "mk --setgid new/notes.txt").is_err());
+        assert!(!dir.path().join("new").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn inherits_mode_from_parent() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = run_command("mk --mode 2750 managed")?;
+        run_command_in(dir.path(), "mk --inherit managed/data")?;
+        run_command_in(dir.path(), "mk --inherit managed/notes.txt managed/run.sh")?;
+
+        let mode = |name: &str| -> anyhow::Result<u32> {
+            Ok(dir.path().join(name).metadata()?.permissions().mode() & 0o7777)
+        };
+        assert_eq!(mode("managed/data")?, 0o2750);
+        assert_eq!(mode("managed/notes.txt")?, 0o640);
+        assert_eq!(mode("managed/run.sh")?, 0o750);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn creates_with_umask() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        in_child_process("creates_with_umask", || {
+            let dir = run_command("mk --umask 027 shared/notes.txt")?;
+
+            let mode = |name: &str| -> anyhow::Result<u32> {
+                Ok(dir.path().join(name).metadata()?.permissions().mode() & 0o777)
+            };
+            assert_eq!(mode("shared")?, 0o750);
+            assert_eq!(mode("shared/notes.txt")?, 0o640);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn errors_editing_with_stdin_content() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let options = Options::try_parse_from("mk -e notes/idea.md".split(" "))?;
+
+        assert!(super::run(dir.path(), options, "content".as_bytes()).is_err());
+        assert!(!dir.path().join("notes").exists());
+        Ok(())
+    }
+
+    fn run_compat_in(dir: &Path, cmd: &str) -> anyhow::Result<()> {
+        let args = cmd.split(' ').map(Into::into).collect::<Vec<_>>();
+        let (tool, args) = compat::detect(&args).context("not a compat invocation")?;
+        run_compat(dir, compat::Invocation::try_parse_from(tool, args)?, |_| {})
+    }
+
+    #[test]
+    fn stands_in_for_mkdir() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        run_compat_in(dir.path(), "mkdir -p logs/v1.2 -m 700")?;
+        run_compat_in(dir.path(), "mk --compat mkdir -p logs/v1.2")?;
+        run_compat_in(dir.path(), "mkdir logs/v1.3")?;
+
+        assert!(dir.path().join("logs/v1.2").is_dir());
+        assert!(dir.path().join("logs/v1.3").is_dir());
+        assert!(run_compat_in(dir.path(), "mkdir logs/v1.2").is_err());
+        assert!(run_compat_in(dir.path(), "mkdir missing/dir").is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = dir
+                .path()
+                .join("logs/v1.2")
+                .metadata()?
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn stands_in_for_touch() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("old.txt"), "kept")?;
+
+        run_compat_in(dir.path(), "touch -d @1000000000 old.txt dir/new")?;
+        run_compat_in(dir.path(), "touch -c skipped")?;
+
+        let modified =
+            |name: &str| -> anyhow::Result<_> { Ok(dir.path().join(name).metadata()?.modified()?) };
+        let epoch = std::time::SystemTime::UNIX_EPOCH;
+        assert_eq!(
+            modified("old.txt")?,
+            epoch + std::time::Duration::from_secs(1000000000)
+        );
+        assert_eq!(std::fs::read_to_string(dir.path().join("old.txt"))?, "kept");
+        assert!(dir.path().join("dir/new").is_file());
+        assert!(!dir.path().join("skipped").exists());
+
+        run_compat_in(dir.path(), "touch -m -r old.txt dir/new")?;
+        assert_eq!(modified("dir/new")?, modified("old.txt")?);
+        Ok(())
+    }
+
+    #[test]
+    fn inits_shell_without_paths() -> anyhow::Result<()> {
+        run_command("mk --init-shell bash")?;
+        assert!(run_command("mk --init-shell bash foo").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn fills_file_with_pattern() -> anyhow::Result<()> {
+        let dir = run_command("mk --size 1K --fill 0xAB testdata.bin")?;
+
+        assert_eq!(
+            std::fs::read(dir.path().join("testdata.bin"))?,
+            [0xab; 1024]
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn errors_without_free_space() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        let err =
+            run_command_in(dir.path(), "mk --size 1000000T --fill zero huge.bin").unwrap_err();
+        assert!(err.to_string().contains("Not enough space"));
+        assert!(!dir.path().join("huge.bin").exists());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn errors_copying_more_than_free_space() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let free = mk::platform::free_space(dir.path())?.expect("known on unix");
+        // Sparse, so the source itself takes no space.
+        std::fs::File::create(dir.path().join("huge.img"))?.set_len(free + (1 << 30))?;
+
+        let err = run_command_in(dir.path(), "mk --from huge.img copy.img").unwrap_err();
+        assert!(err.to_string().contains("Not enough space"));
+        assert!(!dir.path().join("copy.img").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_fill_without_size() -> anyhow::Result<()> {
+        assert!(run_command("mk --fill random testdata.bin").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn exits_by_failure_kind() -> anyhow::Result<()> {
+        let dir = run_command("mk foo.txt")?;
+        let code = |cmd: &str| exit::code(&run_command_in(dir.path(), cmd).unwrap_err());
+
+        assert_eq!(code("mk foo.txt"), exit::ALREADY_EXISTS);
+        assert_eq!(code("mk --check-case FOO.txt"), exit::ALREADY_EXISTS);
+        assert_eq!(code("mk foo.txt/bar.txt"), exit::TYPE_CONFLICT);
+        assert_eq!(code("mk --no-such-flag foo"), exit::INVALID_ARGUMENTS);
+        assert_eq!(code("mk --restrict ../foo"), exit::REFUSED_PATH);
+        assert_eq!(code(&format!("mk {}", "a".repeat(256))), exit::REFUSED_PATH);
+        assert_eq!(code("mk -f -d foo"), exit::INVALID_ARGUMENTS);
+        assert_eq!(code("mk --hash-name a b"), exit::INVALID_ARGUMENTS);
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(unix)]
+use anyhow::Context;
+use std::path::Path;
+
+/// The setgid bit, which makes entries in a directory take its group.
+pub const SETGID: u32 = 0o2000;
+
+/// The sticky bit, which keeps others from removing entries in a directory, as in `/tmp`.
+pub const STICKY: u32 = 0o1000;
+
+pub use mk::config::parse_mode as parse;
+
+/// Sets the mode of `path` to `mode`, or keeps its current mode without one, and adds the
+/// `special` bits.
+#[cfg(unix)]
+pub fn apply(path: &Path, mode: Option<u32>, special: u32) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = match mode {
+        Some(mode) => mode,
+        None => path.metadata()?.permissions().mode() & 0o7777,
+    };
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode | special))
+        .with_context(|| format!("Unable to set mode of {}", path.display()))
+}
+
+/// Modes have no meaning outside unix, so there's nothing to set.
+#[cfg(not(unix))]
+pub fn apply(_: &Path, _: Option<u32>, _: u32) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Gives `path` the permission bits and group of its parent directory. Files only keep the
+/// execute bits they already had, so they don't all become executable like their directory.
+#[cfg(unix)]
+pub fn inherit(path: &Path, is_file: bool) -> anyhow::Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let parent = path.parent().expect("joined with root");
+    let parent = parent
+        .metadata()
+        .with_context(|| format!("Unable to read {}", parent.display()))?;
+    let inherited = parent.mode() & 0o7777;
+    let mode = if is_file {
+        let current = path.metadata()?.mode();
+        (inherited & 0o666) | (inherited & current & 0o111)
+    } else {
+        inherited
+    };
+    std::os::unix::fs::chown(path, None, Some(parent.gid()))
+        .with_context(|| format!("Unable to set group of {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Unable to set mode of {}", path.display()))
+}
+
+#[cfg(not(unix))]
+pub fn inherit(_: &Path, _: bool) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Restores the previous umask when dropped.
+pub struct Umask(#[cfg(unix)] libc::mode_t);
+
+/// Makes `mask` the process umask until the returned guard is dropped. Outside unix there is no
+/// umask, so nothing changes.
+pub fn umask(mask: u32) -> Umask {
+    #[cfg(unix)]
+    {
+        // SAFETY: umask can't fail, it only swaps the process's mask.
+        Umask(unsafe { libc::umask(mask as libc::mode_t) })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = mask;
+        Umask()
+    }
+}
+
+impl Drop for Umask {
+    fn drop(&mut self) {
+        // SAFETY: as above.
+        #[cfg(unix)]
+        unsafe {
+            libc::umask(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_octal_modes() -> anyhow::Result<()> {
+        assert_eq!(parse("755")?, 0o755);
+        assert_eq!(parse("2775")?, 0o775 | SETGID);
+        assert!(parse("u+x").is_err());
+        assert!(parse("17777").is_err());
+        Ok(())
+    }
+}
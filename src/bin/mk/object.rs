@@ -0,0 +1,131 @@
+use anyhow::Context;
+use std::path::Path;
+
+/// An object storage service, reached through its command line tool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Store {
+    S3,
+    Gcs,
+}
+
+/// A key in a bucket, like `s3://bucket/reports/q3.csv`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Object {
+    pub store: Store,
+    pub bucket: String,
+    pub key: String,
+}
+
+impl std::fmt::Display for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scheme = match self.store {
+            Store::S3 => "s3",
+            Store::Gcs => "gs",
+        };
+        write!(f, "{scheme}://{}/{}", self.bucket, self.key)
+    }
+}
+
+/// Reads `path` as an `s3://` or `gs://` URL.
+pub fn parse(path: &Path) -> Option<Object> {
+    let s = path.to_str()?;
+    let (store, rest) = if let Some(rest) = s.strip_prefix("s3://") {
+        (Store::S3, rest)
+    } else {
+        (Store::Gcs, s.strip_prefix("gs://")?)
+    };
+    let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+    Some(Object {
+        store,
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+    })
+}
+
+impl Object {
+    /// The same object with `key` instead.
+    pub fn with_key(&self, key: impl Into<String>) -> Object {
+        Object {
+            key: key.into(),
+            ..self.clone()
+        }
+    }
+
+    pub fn exists(&self) -> anyhow::Result<bool> {
+        let output = match self.store {
+            Store::S3 => {
+                let mut cli = std::process::Command::new("aws");
+                cli.args(["s3api", "head-object", "--bucket", &self.bucket, "--key"])
+                    .arg(&self.key);
+                cli
+            }
+            Store::Gcs => {
+                let mut cli = std::process::Command::new("gcloud");
+                cli.args(["storage", "objects", "describe"])
+                    .arg(self.to_string());
+                cli
+            }
+        }
+        .output()
+        .context("Unable to check for existing object")?;
+        Ok(output.status.success())
+    }
+
+    /// Uploads `contents` as the object. The command line tools stream stdin, switching to
+    /// multipart uploads for large contents.
+    pub fn upload(&self, contents: &mut impl std::io::Read) -> anyhow::Result<()> {
+        let mut cli = match self.store {
+            // `aws s3 cp` can't write keys that end in a slash, which directories are.
+            Store::S3 if self.key.ends_with('/') => {
+                let mut cli = std::process::Command::new("aws");
+                cli.args(["s3api", "put-object", "--bucket", &self.bucket, "--key"])
+                    .arg(&self.key);
+                cli
+            }
+            Store::S3 => {
+                let mut cli = std::process::Command::new("aws");
+                cli.args(["s3", "cp", "-"]).arg(self.to_string());
+                cli
+            }
+            Store::Gcs => {
+                let mut cli = std::process::Command::new("gcloud");
+                cli.args(["storage", "cp", "-"]).arg(self.to_string());
+                cli
+            }
+        };
+        let mut child = cli
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .context("Unable to run the object storage command line tool")?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        std::io::copy(contents, &mut stdin)?;
+        drop(stdin);
+
+        let status = child.wait()?;
+        anyhow::ensure!(status.success(), "Upload exited with {status}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bucket_urls() {
+        assert_eq!(
+            parse(Path::new("s3://bucket/reports/q3.csv")),
+            Some(Object {
+                store: Store::S3,
+                bucket: "bucket".to_string(),
+                key: "reports/q3.csv".to_string(),
+            })
+        );
+        assert_eq!(
+            parse(Path::new("gs://bucket")).map(|object| object.to_string()),
+            Some("gs://bucket/".to_string())
+        );
+        assert_eq!(parse(Path::new("bucket/reports")), None);
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::Context;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Finds the `mk-<name>` executable in the directories of `search`, a `PATH`-style list.
+fn find(name: &str, search: &OsStr) -> anyhow::Result<PathBuf> {
+    let program = format!("mk-{name}{}", std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(search)
+        .map(|dir| dir.join(&program))
+        .find(|path| path.is_file())
+        .with_context(|| format!("No plugin {program} on PATH"))
+}
+
+/// Runs the plugin `name` as `mk-<name> <hook> <path>`, failing with its stderr.
+fn run(
+    name: &str,
+    hook: &str,
+    path: &Path,
+    stdout: std::process::Stdio,
+    search: &OsStr,
+) -> anyhow::Result<Vec<u8>> {
+    let program = find(name, search)?;
+    tracing::debug!(program = %program.display(), hook, path = %path.display(), "running plugin");
+    let output = std::process::Command::new(program)
+        .arg(hook)
+        .arg(path)
+        .stdin(std::process::Stdio::null())
+        .stdout(stdout)
+        .output()
+        .with_context(|| format!("Unable to run plugin {name}"))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "Plugin {name} failed on {}: {}",
+        path.display(),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(output.stdout)
+}
+
+/// Passes `path` through each plugin's `path` hook in turn. A plugin that prints nothing leaves
+/// the path as it is, otherwise its first line is the new path.
+pub fn path(names: &[String], path: &Path, search: &OsStr) -> anyhow::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    for name in names {
+        let output = run(name, "path", &path, std::process::Stdio::piped(), search)?;
+        let output = String::from_utf8(output)
+            .with_context(|| format!("Plugin {name} printed a path that isn't UTF-8"))?;
+        if let Some(line) = output.lines().next().filter(|line| !line.is_empty()) {
+            path = line.into();
+        }
+    }
+    Ok(path)
+}
+
+/// Appends what each plugin's `content` hook prints to `file`.
+pub fn content(names: &[String], file: &Path, search: &OsStr) -> anyhow::Result<()> {
+    for name in names {
+        let out = std::fs::OpenOptions::new().append(true).open(file)?;
+        run(name, "content", file, out.into(), search)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// A directory holding a `mk-<name>` plugin that runs `script` with `sh`.
+    fn plugin(name: &str, script: &str) -> anyhow::Result<tempfile::TempDir> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(format!("mk-{name}"));
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n"))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        Ok(dir)
+    }
+
+    #[test]
+    fn transforms_paths() -> anyhow::Result<()> {
+        let dir = plugin(
+            "dated",
+            r#"if [ "$1" = path ]; then echo "2024-01-02-$(basename "$2")"; fi"#,
+        )?;
+        let search = dir.path().as_os_str();
+        let names = ["dated".to_string()];
+
+        assert_eq!(
+            path(&names, Path::new("post.md"), search)?,
+            Path::new("2024-01-02-post.md")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn generates_contents() -> anyhow::Result<()> {
+        let dir = plugin(
+            "title",
+            r##"if [ "$1" = content ]; then echo "# $(basename "$2" .md)"; fi"##,
+        )?;
+        let file = dir.path().join("notes.md");
+        std::fs::write(&file, "")?;
+
+        let names = ["title".to_string()];
+        assert_eq!(path(&names, &file, dir.path().as_os_str())?, file);
+        content(&names, &file, dir.path().as_os_str())?;
+
+        assert_eq!(std::fs::read_to_string(&file)?, "# notes\n");
+        Ok(())
+    }
+
+    #[test]
+    fn reports_failures() -> anyhow::Result<()> {
+        let dir = plugin("broken", "echo oops >&2; exit 1")?;
+        let names = ["broken".to_string()];
+
+        let err = path(&names, Path::new("a.txt"), dir.path().as_os_str()).unwrap_err();
+        assert!(err.to_string().contains("oops"));
+        assert!(
+            path(
+                &["missing".to_string()],
+                Path::new("a.txt"),
+                dir.path().as_os_str()
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+use anyhow::Context;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A path to make on another host over ssh.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Target {
+    pub host: String,
+    pub path: PathBuf,
+}
+
+/// Reads `path` as a remote target: any path when `host` is given, otherwise an
+/// `ssh://[USER@]HOST[:PORT]/PATH` URL or scp's `USER@HOST:PATH`, and `HOST:PATH` without a user
+/// only when `scp` is set, so that names like `meeting:notes.md` stay local. A leading `~/` is
+/// dropped, since paths are relative to the remote home directory anyway.
+pub fn target(path: &Path, host: Option<&str>, scp: bool) -> Option<Target> {
+    let s = path.to_str()?;
+    let (host, path) = if let Some(host) = host {
+        (host.to_string(), s)
+    } else if let Some(url) = s.strip_prefix("ssh://") {
+        let (authority, path) = url.split_at(url.find('/').unwrap_or(url.len()));
+        if authority.is_empty() {
+            return None;
+        }
+        // `ssh://host/~/notes.md` is in the home directory, like scp's `host:~/notes.md`.
+        let path = match path.strip_prefix("/~") {
+            Some(rest) => rest.strip_prefix('/').unwrap_or(rest),
+            None => path,
+        };
+        (format!("ssh://{authority}"), path)
+    } else {
+        let (host, path) = s.split_once(':')?;
+        // Slashes before the colon make it a local path, single letters are drive letters, and
+        // `scheme://` is a URL.
+        if host.len() < 2 || host.contains(std::path::is_separator) || path.starts_with("//") {
+            return None;
+        }
+        if !scp && !host.contains('@') {
+            return None;
+        }
+        (host.to_string(), path)
+    };
+    let path = match path.strip_prefix('~') {
+        Some("") => ".",
+        Some(rest) if rest.starts_with('/') => rest.trim_start_matches('/'),
+        _ if path.is_empty() => ".",
+        _ => path,
+    };
+    Some(Target {
+        host,
+        path: path.into(),
+    })
+}
+
+impl Target {
+    /// How `path` on this target's host is written back to the user, in the form it was given.
+    pub fn display(&self, path: &Path) -> String {
+        match self.host.starts_with("ssh://") {
+            true if path.is_absolute() => format!("{}{}", self.host, path.display()),
+            true => format!("{}/~/{}", self.host, path.display()),
+            false => format!("{}:{}", self.host, path.display()),
+        }
+    }
+}
+
+/// How the remote script treats what is already there.
+pub struct Existing {
+    pub overwrite: bool,
+    pub recursive: bool,
+    pub parents: bool,
+}
+
+/// The permissions the remote script gives a new entry.
+pub struct Permissions {
+    pub executable: bool,
+    /// Replaces the remote umask's mode, like `--mode`.
+    pub mode: Option<u32>,
+    /// Setgid and sticky bits to add.
+    pub special: u32,
+}
+
+/// A POSIX shell script that makes `path` as a file with stdin as its contents, or as a
+/// directory.
+pub fn script(
+    path: &Path,
+    is_file: bool,
+    permissions: &Permissions,
+    existing: &Existing,
+) -> String {
+    let mut script = format!("set -e\np={}\n", quote(&path.to_string_lossy()));
+    if !existing.overwrite {
+        script += "if [ -e \"$p\" ]; then echo \"Entry $p already exists\" >&2; exit 1; fi\n";
+    } else if is_file && existing.recursive {
+        script += "if [ -d \"$p\" ]; then rm -rf -- \"$p\"; fi\n";
+    } else if is_file {
+        script += "if [ -d \"$p\" ]; then rmdir -- \"$p\"; fi\n";
+    } else {
+        script += "if [ -e \"$p\" ] && [ ! -d \"$p\" ]; then rm -f -- \"$p\"; fi\n";
+    }
+
+    script += "d=$(dirname -- \"$p\")\n";
+    if existing.parents {
+        script += "mkdir -p -- \"$d\"\n";
+    } else {
+        script += "if [ ! -d \"$d\" ]; then echo \"Parent directory $d does not exist\" >&2; exit 1; fi\n";
+    }
+
+    if is_file {
+        script += "cat > \"$p\"\n";
+        if permissions.executable && permissions.mode.is_none() {
+            script += "chmod +x -- \"$p\"\n";
+        }
+    } else {
+        script += "mkdir -p -- \"$p\"\n";
+    }
+    match permissions.mode {
+        Some(mode) => script += &format!("chmod {:o} -- \"$p\"\n", mode | permissions.special),
+        None if permissions.special != 0 => {
+            let mut bits = Vec::new();
+            if permissions.special & crate::mode::SETGID != 0 {
+                bits.push("g+s");
+            }
+            if permissions.special & crate::mode::STICKY != 0 {
+                bits.push("+t");
+            }
+            script += &format!("chmod {} -- \"$p\"\n", bits.join(","));
+        }
+        None => {}
+    }
+    script
+}
+
+/// Runs `script` on `host` with `ssh`, feeding it `contents` on stdin. The remote shell's errors
+/// go straight to our stderr.
+pub fn run(host: &str, script: &str, contents: &[u8]) -> anyhow::Result<()> {
+    let mut child = ssh(host, script)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .context("Unable to run ssh")?;
+    // Taken for the write alone, so the pipe closes and the remote side sees the end.
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(contents)?;
+
+    let status = child.wait()?;
+    anyhow::ensure!(status.success(), "ssh to {host} exited with {status}");
+    Ok(())
+}
+
+/// The names of the entries in `dir` on `host`, or none if it doesn't exist there.
+pub fn list(host: &str, dir: &Path) -> anyhow::Result<Vec<String>> {
+    let script = format!(
+        "if [ -d {dir} ]; then ls -1A -- {dir}; fi",
+        dir = quote(&dir.to_string_lossy())
+    );
+    let output = ssh(host, &script)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .context("Unable to run ssh")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "ssh to {host} exited with {}",
+        output.status
+    );
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+fn ssh(host: &str, script: &str) -> std::process::Command {
+    let mut ssh = std::process::Command::new("ssh");
+    ssh.arg("--")
+        .arg(host)
+        .arg(format!("sh -c {}", quote(script)));
+    ssh
+}
+
+/// Quotes `s` as a single word for a POSIX shell.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(host: &str, path: &str) -> Option<Target> {
+        Some(Target {
+            host: host.to_string(),
+            path: path.into(),
+        })
+    }
+
+    #[test]
+    fn reads_scp_style_targets() {
+        assert_eq!(
+            target(Path::new("me@web:/srv/app/releases/"), None, false),
+            remote("me@web", "/srv/app/releases/")
+        );
+        assert_eq!(
+            target(Path::new("web:~/notes.md"), None, true),
+            remote("web", "notes.md")
+        );
+        assert_eq!(
+            target(Path::new("notes.md"), Some("web"), false),
+            remote("web", "notes.md")
+        );
+    }
+
+    #[test]
+    fn reads_ssh_urls() {
+        let url = target(Path::new("ssh://me@web:2222/srv/app/"), None, false);
+        assert_eq!(url, remote("ssh://me@web:2222", "/srv/app/"));
+        assert_eq!(
+            target(Path::new("ssh://web/~/notes.md"), None, false),
+            remote("ssh://web", "notes.md")
+        );
+
+        let url = url.unwrap();
+        assert_eq!(
+            url.display(Path::new("/srv/app/1")),
+            "ssh://me@web:2222/srv/app/1"
+        );
+        assert_eq!(url.display(Path::new("a.md")), "ssh://me@web:2222/~/a.md");
+    }
+
+    #[test]
+    fn leaves_local_paths() {
+        assert_eq!(target(Path::new("notes.md"), None, false), None);
+        assert_eq!(target(Path::new("meeting:notes.md"), None, false), None);
+        assert_eq!(target(Path::new("./a:b.md"), None, true), None);
+        assert_eq!(target(Path::new("C:/Users/me"), None, true), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn script_makes_entries() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let existing = Existing {
+            overwrite: false,
+            recursive: false,
+            parents: true,
+        };
+        let permissions = |executable, mode| Permissions {
+            executable,
+            mode,
+            special: 0,
+        };
+        let sh = |script: String, stdin: &str| -> anyhow::Result<bool> {
+            let mut child = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(script)
+                .current_dir(dir.path())
+                .stdin(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::null())
+                .spawn()?;
+            child.stdin.take().unwrap().write_all(stdin.as_bytes())?;
+            Ok(child.wait()?.success())
+        };
+
+        assert!(sh(
+            script(
+                Path::new("it's/run.sh"),
+                true,
+                &permissions(true, None),
+                &existing
+            ),
+            "echo hi"
+        )?);
+        assert!(sh(
+            script(
+                Path::new("build/out"),
+                false,
+                &permissions(false, None),
+                &existing
+            ),
+            ""
+        )?);
+        assert!(!sh(
+            script(
+                Path::new("build/out"),
+                false,
+                &permissions(false, None),
+                &existing
+            ),
+            ""
+        )?);
+        assert!(sh(
+            script(
+                Path::new("key"),
+                true,
+                &permissions(false, Some(0o600)),
+                &existing
+            ),
+            "secret"
+        )?);
+
+        let file = dir.path().join("it's/run.sh");
+        assert_eq!(std::fs::read_to_string(&file)?, "echo hi");
+        assert!(
+            std::os::unix::fs::PermissionsExt::mode(&file.metadata()?.permissions()) & 0o111 != 0
+        );
+        assert!(dir.path().join("build/out").is_dir());
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(
+                &dir.path().join("key").metadata()?.permissions()
+            ) & 0o777,
+            0o600
+        );
+        Ok(())
+    }
+}
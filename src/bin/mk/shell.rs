@@ -0,0 +1,42 @@
+/// Shells `--init-shell` can print a wrapper for.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// A `mkcd` function for `shell` that makes its arguments and changes into the last directory
+/// `mk --print-dir` reports.
+pub fn init(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash | Shell::Zsh => {
+            r#"mkcd() {
+    local dirs
+    dirs="$(command mk --print-dir "$@")" || return
+    cd -- "${dirs##*$'\n'}"
+}
+"#
+        }
+        Shell::Fish => {
+            r#"function mkcd
+    set -l dirs (command mk --print-dir $argv); or return
+    cd -- $dirs[-1]
+end
+"#
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_print_dir() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            assert!(init(shell).contains("mkcd"));
+            assert!(init(shell).contains("command mk --print-dir"));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+/// Parses a byte count like `512`, `4K`, `2G`, `1.5MiB`, or `10MB`.
+///
+/// Single-letter suffixes and `iB` suffixes are powers of 1024, `B` suffixes powers of 1000.
+pub fn parse(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, suffix) = s.split_at(split);
+
+    let power = match suffix.trim_start().to_ascii_uppercase().as_str() {
+        "" | "B" => return number.parse().map_err(|_| invalid(s)),
+        "K" | "KIB" => 1024u64,
+        "M" | "MIB" => 1024u64.pow(2),
+        "G" | "GIB" => 1024u64.pow(3),
+        "T" | "TIB" => 1024u64.pow(4),
+        "KB" => 1000,
+        "MB" => 1000u64.pow(2),
+        "GB" => 1000u64.pow(3),
+        "TB" => 1000u64.pow(4),
+        _ => return Err(invalid(s)),
+    };
+    // Whole numbers are multiplied exactly, so only fractions go through floats.
+    if let Ok(whole) = number.parse::<u64>() {
+        return whole.checked_mul(power).ok_or_else(|| too_large(s));
+    }
+    let bytes = number.parse::<f64>().map_err(|_| invalid(s))? * power as f64;
+    // `u64::MAX as f64` rounds up to 2^64, the first value that doesn't fit.
+    if bytes >= u64::MAX as f64 {
+        return Err(too_large(s));
+    }
+    Ok(bytes as u64)
+}
+
+/// Formats a byte count for people, like `512 B` or `1.5 MiB`.
+pub fn format(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+fn invalid(s: &str) -> anyhow::Error {
+    anyhow::anyhow!("Invalid size {s}, expected something like 512, 4K, or 2GiB")
+}
+
+fn too_large(s: &str) -> anyhow::Error {
+    anyhow::anyhow!("Size {s} is more than {} bytes", u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_bytes() -> anyhow::Result<()> {
+        assert_eq!(parse("512")?, 512);
+        assert_eq!(parse("512B")?, 512);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_binary_suffixes() -> anyhow::Result<()> {
+        assert_eq!(parse("4K")?, 4096);
+        assert_eq!(parse("2G")?, 2 * 1024 * 1024 * 1024);
+        assert_eq!(parse("1.5MiB")?, 1024 * 1024 * 3 / 2);
+        assert_eq!(parse("3 t")?, 3 * 1024u64.pow(4));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_decimal_suffixes() -> anyhow::Result<()> {
+        assert_eq!(parse("10MB")?, 10_000_000);
+        Ok(())
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format(512), "512 B");
+        assert_eq!(format(1536), "1.5 KiB");
+        assert_eq!(format(2 * 1024u64.pow(3)), "2.0 GiB");
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        assert!(parse("").is_err());
+        assert!(parse("G").is_err());
+        assert!(parse("1.5").is_err());
+        assert!(parse("2X").is_err());
+    }
+
+    #[test]
+    fn rejects_sizes_past_u64() -> anyhow::Result<()> {
+        assert!(parse("16777216T").is_err());
+        assert!(parse("16777216.5T").is_err());
+        assert!(parse("99999999999999999999").is_err());
+        assert_eq!(parse("16777215T")?, 16777215 * 1024u64.pow(4));
+        Ok(())
+    }
+}
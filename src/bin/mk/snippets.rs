@@ -0,0 +1,180 @@
+use anyhow::Context;
+use mk::config::Config;
+use mk::expand;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// What `mk snippets` does with the snippet library.
+#[derive(clap::Subcommand, Debug)]
+pub enum Action {
+    /// List the snippets in the library with the variables they take.
+    List,
+
+    /// Import <FILE> into the library as a new snippet.
+    Add {
+        file: PathBuf,
+
+        /// Name the snippet <NAME> instead of after <FILE>.
+        #[clap(long, value_name = "NAME")]
+        name: Option<String>,
+
+        /// Replace a snippet that already has the name.
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Print the snippet <NAME>.
+    Show { name: String },
+}
+
+/// The snippet library from `snippets` in `config`, relative to `root`, or the default one.
+pub fn library(config: &Config, root: &Path) -> anyhow::Result<PathBuf> {
+    match &config.snippets {
+        Some(dir) => Ok(root.join(expand::tilde(dir, dirs::home_dir().as_deref())?)),
+        None => Config::default_snippets()
+            .context("No snippet library, set `snippets` in the config to a directory"),
+    }
+}
+
+/// The snippet called `name` in `library`, if `name` is a plain file name and it exists.
+pub fn find(library: &Path, name: &Path) -> Option<PathBuf> {
+    let path = library.join(name);
+    (is_plain(name) && path.is_file()).then_some(path)
+}
+
+/// Whether `name` is a single file name, so it can't reach outside the library.
+fn is_plain(name: &Path) -> bool {
+    let mut components = name.components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+/// Does `action` on `library`, with paths relative to `root` and output to `out`.
+pub fn run(action: Action, library: &Path, root: &Path, out: &mut dyn Write) -> anyhow::Result<()> {
+    match action {
+        Action::List => list(library, out),
+        Action::Add { file, name, force } => add(library, &root.join(&file), name, force),
+        Action::Show { name } => {
+            let path = find(library, Path::new(&name))
+                .with_context(|| format!("No snippet {name} in {}", library.display()))?;
+            out.write_all(&std::fs::read(&path)?)?;
+            Ok(())
+        }
+    }
+}
+
+/// Writes a line for each snippet, with its name and then its variables like `author=default`.
+fn list(library: &Path, out: &mut dyn Write) -> anyhow::Result<()> {
+    let entries = match std::fs::read_dir(library) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        result => result.with_context(|| format!("Unable to read {}", library.display()))?,
+    };
+    let mut snippets = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            snippets.push(entry.path());
+        }
+    }
+    snippets.sort();
+
+    for path in snippets {
+        let name = path.file_name().expect("read from a directory");
+        let variables = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| mk::template::variables(&contents))
+            .with_context(|| format!("Invalid snippet {}", path.display()))?
+            .into_iter()
+            .map(|(name, default)| match default {
+                Some(default) => format!("{name}={default}"),
+                None => name,
+            })
+            .collect::<Vec<_>>();
+        writeln!(out, "{}\t{}", name.to_string_lossy(), variables.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Copies `file` into `library` as the snippet `name`, or the file's own name.
+fn add(library: &Path, file: &Path, name: Option<String>, force: bool) -> anyhow::Result<()> {
+    let contents = std::fs::read_to_string(file)
+        .with_context(|| format!("Unable to read {}", file.display()))?;
+    mk::template::variables(&contents)
+        .with_context(|| format!("Invalid snippet {}", file.display()))?;
+
+    let name = match name {
+        Some(name) => PathBuf::from(name),
+        None => PathBuf::from(file.file_name().context("Snippet file has no name")?),
+    };
+    anyhow::ensure!(
+        is_plain(&name),
+        "Snippet name {} must be a plain file name",
+        name.display()
+    );
+
+    let path = library.join(&name);
+    anyhow::ensure!(
+        force || !path.exists(),
+        "Snippet {} already exists, replace it with --force",
+        name.display()
+    );
+    std::fs::create_dir_all(library)
+        .with_context(|| format!("Unable to make {}", library.display()))?;
+    std::fs::write(&path, contents).with_context(|| format!("Unable to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_lists_and_shows_snippets() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let library = dir.path().join("snippets");
+        std::fs::write(
+            dir.path().join("post.md"),
+            "# {{title}}\nby {{author:me}}\n",
+        )?;
+        let run = |action| {
+            let mut out = Vec::new();
+            run(action, &library, dir.path(), &mut out).map(|()| out)
+        };
+
+        run(Action::Add {
+            file: "post.md".into(),
+            name: None,
+            force: false,
+        })?;
+        run(Action::Add {
+            file: "post.md".into(),
+            name: Some("note".into()),
+            force: false,
+        })?;
+        let again = Action::Add {
+            file: "post.md".into(),
+            name: None,
+            force: false,
+        };
+        assert!(run(again).is_err());
+
+        assert_eq!(
+            run(Action::List)?,
+            b"note\ttitle author=me\npost.md\ttitle author=me\n"
+        );
+        assert_eq!(
+            run(Action::Show {
+                name: "post.md".into()
+            })?,
+            b"# {{title}}\nby {{author:me}}\n"
+        );
+        assert!(
+            run(Action::Show {
+                name: "../post.md".into()
+            })
+            .is_err()
+        );
+        Ok(())
+    }
+}
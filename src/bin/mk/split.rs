@@ -0,0 +1,93 @@
+use std::path::{Component, PathBuf};
+
+/// Carves `contents` into files at lines like `--- path/to/file.ext`, where `marker` is `---`.
+///
+/// Each section runs until the next marker line. Anything before the first marker must be blank.
+pub fn sections(contents: &[u8], marker: &str) -> anyhow::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut sections = Vec::<(PathBuf, Vec<u8>)>::new();
+    for line in contents.split_inclusive(|&b| b == b'\n') {
+        if let Some(name) = marker_name(line, marker) {
+            sections.push((parse_name(name)?, Vec::new()));
+            continue;
+        }
+        match sections.last_mut() {
+            Some((_, section)) => section.extend_from_slice(line),
+            None => anyhow::ensure!(
+                line.trim_ascii().is_empty(),
+                "Input does not start with a {marker} line"
+            ),
+        }
+    }
+    anyhow::ensure!(!sections.is_empty(), "No {marker} lines in input");
+    Ok(sections)
+}
+
+/// The name following `marker` if `line` is a marker line.
+fn marker_name<'l>(line: &'l [u8], marker: &str) -> Option<&'l [u8]> {
+    let rest = line.strip_prefix(marker.as_bytes())?;
+    if !rest.first()?.is_ascii_whitespace() {
+        return None;
+    }
+    Some(rest.trim_ascii()).filter(|name| !name.is_empty())
+}
+
+/// Parses a section name, refusing any that would lead outside the target directory.
+fn parse_name(name: &[u8]) -> anyhow::Result<PathBuf> {
+    let name = PathBuf::from(std::str::from_utf8(name)?);
+    anyhow::ensure!(
+        name.components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir)),
+        "Section {} leads outside of the target directory",
+        name.display()
+    );
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_marker_lines() -> anyhow::Result<()> {
+        let input = b"--- a.txt\nfirst\n\n--- src/b.rs\nfn main() {}\n";
+
+        assert_eq!(
+            sections(input, "---")?,
+            [
+                (PathBuf::from("a.txt"), b"first\n\n".to_vec()),
+                (PathBuf::from("src/b.rs"), b"fn main() {}\n".to_vec()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn skips_leading_blank_lines() -> anyhow::Result<()> {
+        assert_eq!(
+            sections(b"\n  \n=== a.txt\r\nx", "===")?,
+            [(PathBuf::from("a.txt"), b"x".to_vec())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_lines_that_only_start_like_markers() -> anyhow::Result<()> {
+        assert_eq!(
+            sections(b"--- a.md\n----\n---\n", "---")?,
+            [(PathBuf::from("a.md"), b"----\n---\n".to_vec())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_content_before_first_marker() {
+        assert!(sections(b"preamble\n--- a.txt\n", "---").is_err());
+        assert!(sections(b"no markers", "---").is_err());
+    }
+
+    #[test]
+    fn errors_with_escaping_names() {
+        assert!(sections(b"--- ../a.txt\n", "---").is_err());
+        assert!(sections(b"--- /etc/passwd\n", "---").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use crate::size;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// What an invocation made, printed by `--summary`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub directories: usize,
+    pub files: usize,
+    pub bytes: u64,
+    pub skipped: usize,
+    pub elapsed: Duration,
+}
+
+/// Files and links made or written to, and the bytes written into them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Written {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Written {
+    /// Adds a file that had `bytes` written into it.
+    pub fn add(self, bytes: u64) -> Written {
+        Written {
+            files: self.files + 1,
+            bytes: self.bytes + bytes,
+        }
+    }
+}
+
+impl Summary {
+    /// Tallies the directories among the `created` entries as they are now, and the `written`
+    /// files. Files made along the way, like .gitkeep, aren't counted.
+    pub fn new(
+        created: &[PathBuf],
+        written: Written,
+        skipped: usize,
+        elapsed: Duration,
+    ) -> Summary {
+        let directories = created
+            .iter()
+            .filter_map(|path| path.symlink_metadata().ok())
+            .filter(|metadata| metadata.is_dir())
+            .count();
+        Summary {
+            directories,
+            files: written.files,
+            bytes: written.bytes,
+            skipped,
+            elapsed,
+        }
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural =
+            |n: usize, one: &str, many: &str| format!("{n} {}", if n == 1 { one } else { many });
+        write!(
+            f,
+            "Made {} and {} with {} in {:.2?}, skipped {}",
+            plural(self.directories, "directory", "directories"),
+            plural(self.files, "file", "files"),
+            size::format(self.bytes),
+            self.elapsed,
+            plural(self.skipped, "entry", "entries"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tallies_created_entries() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir(dir.path().join("src"))?;
+        std::fs::write(dir.path().join("src/.gitkeep"), "")?;
+        let created = ["src", "src/.gitkeep", "gone"].map(|p| dir.path().join(p));
+        let written = Written::default().add(2048).add(0);
+
+        let summary = Summary::new(&created, written, 1, Duration::from_millis(5));
+
+        assert_eq!(
+            summary.to_string(),
+            "Made 1 directory and 2 files with 2.0 KiB in 5.00ms, skipped 1 entry"
+        );
+        Ok(())
+    }
+}
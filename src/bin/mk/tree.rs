@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+/// Characters that indent an outline, including the box drawing of `tree`'s output.
+const INDENT: [char; 8] = [' ', '\t', '\u{a0}', '│', '├', '└', '─', '|'];
+
+/// Turns an indented outline into the paths to make, with entries that have children indented
+/// under them made as directories:
+///
+/// ```text
+/// src/
+///   main.rs
+///   bin
+///     tool.rs
+/// ```
+///
+/// The `.` root line and closing summary of `tree`'s output are skipped, so it can be replayed.
+pub fn parse(outline: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let mut entries = Vec::<(usize, String)>::new();
+    let mut parents = Vec::<(usize, String)>::new();
+    for line in outline.lines() {
+        let name = line.trim_start_matches(INDENT).trim_end();
+        if name.is_empty() || name == "." || is_summary(name) {
+            continue;
+        }
+        anyhow::ensure!(
+            !name.starts_with('/'),
+            "Outline entry {name} must be relative"
+        );
+        let depth = line.chars().take_while(|c| INDENT.contains(c)).count();
+        while parents.last().is_some_and(|(d, _)| *d >= depth) {
+            parents.pop();
+        }
+
+        let path = match parents.last() {
+            Some((_, parent)) => format!("{}/{name}", parent.trim_end_matches('/')),
+            None => name.to_string(),
+        };
+        if let Some(last) = entries.last_mut()
+            && last.0 < depth
+            && !last.1.ends_with('/')
+        {
+            last.1.push('/');
+        }
+        parents.push((depth, path.clone()));
+        entries.push((depth, path));
+    }
+    Ok(entries.into_iter().map(|(_, path)| path.into()).collect())
+}
+
+/// Whether `line` is a summary like `3 directories, 5 files`.
+fn is_summary(line: &str) -> bool {
+    line.split(", ").all(|part| {
+        part.split_once(' ').is_some_and(|(count, noun)| {
+            count.bytes().all(|b| b.is_ascii_digit())
+                && ["directory", "directories", "file", "files"].contains(&noun)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_indented_outline() -> anyhow::Result<()> {
+        let paths = parse("src/\n  main.rs\n  bin\n    tool.rs\n\nREADME.md\n")?;
+
+        assert_eq!(
+            paths,
+            [
+                "src/",
+                "src/main.rs",
+                "src/bin/",
+                "src/bin/tool.rs",
+                "README.md"
+            ]
+            .map(PathBuf::from)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_tree_output() -> anyhow::Result<()> {
+        let output = ".\n├── docs\n│   └── v1.2\n└── main.rs\n\n2 directories, 1 file\n";
+
+        assert_eq!(
+            parse(output)?,
+            ["docs/", "docs/v1.2", "main.rs"].map(PathBuf::from)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use crate::language::Language;
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the project config file, discovered by walking up from the working directory.
+pub const PROJECT_FILE: &str = ".mk.toml";
+
+/// Defaults read from `config.toml` in the user config directory, overridden by the nearest
+/// project `.mk.toml`.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Kind of entry to make when neither `-f` nor `-d` is given. Inferred when unset.
+    pub kind: Option<Kind>,
+
+    /// Permissions to give new entries when `--mode` isn't given, in octal like `"755"`.
+    #[serde(deserialize_with = "octal_mode")]
+    pub mode: Option<u32>,
+
+    /// First line of new files made with a profile, like `#!/bin/sh`. A bare program like `bash`
+    /// is run through `/usr/bin/env`. Only profiles take one, as it would start every file.
+    pub shebang: Option<String>,
+
+    /// Shebangs for new files matching each glob pattern, like `"**/*.sh" = "bash"`, written like
+    /// `shebang`.
+    pub shebangs: BTreeMap<String, String>,
+
+    /// Kind of entry to infer for names like `.config` that start with '.' and have no extension.
+    pub dotfiles: Option<Kind>,
+
+    /// Make new files executable, like `-x`.
+    pub executable: Option<bool>,
+
+    /// Changes to the built-in extensions, like `sh`, that make new files executable.
+    pub executable_extensions: ExtensionOverrides,
+
+    /// Changes to the built-in extension patterns that still infer a directory, like `d` in
+    /// `rollout.d`.
+    pub directory_extensions: ExtensionOverrides,
+
+    /// Write an empty .gitkeep into every new directory, like `--gitkeep`.
+    pub gitkeep: Option<bool>,
+
+    /// Start new source files with built-in boilerplate, like `--boilerplate`.
+    pub boilerplate: Option<bool>,
+    pub git_add: Option<bool>,
+    pub zoxide: Option<bool>,
+
+    /// Command `--edit` opens files with, ahead of `$VISUAL` and `$EDITOR`.
+    pub editor: Option<String>,
+
+    /// Path of the note `--daily` opens, with `strftime` escapes like `%Y` for today's date.
+    pub daily: Option<String>,
+
+    /// Fields added to `--frontmatter` after the title and date, which they can also replace.
+    pub frontmatter: BTreeMap<String, toml::Value>,
+
+    /// Languages for `--type`, added to or layered over the built-in ones.
+    pub types: BTreeMap<String, Language>,
+
+    /// Directory of the snippet library `mk snippets` manages and `--template` also looks in.
+    /// Defaults to `snippets` next to the user config.
+    pub snippets: Option<PathBuf>,
+
+    /// Values for `{{name}}` variables in templates.
+    pub vars: BTreeMap<String, String>,
+
+    /// Commands to run around creating paths that match each glob pattern.
+    pub hooks: BTreeMap<String, Hook>,
+
+    /// Named bundles of settings, selected with `--profile`.
+    pub profile: BTreeMap<String, Config>,
+}
+
+/// Shell commands run before and after creating a matching path. `{path}` in a command is
+/// replaced with the path.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hook {
+    pub pre: Option<String>,
+    pub post: Option<String>,
+}
+
+/// Changes to a built-in list of extensions. Removals win over additions.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtensionOverrides {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    File,
+    #[serde(alias = "dir")]
+    Directory,
+}
+
+impl Config {
+    /// The default location of the config file, following platform conventions.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("mk").join("config.toml"))
+    }
+
+    /// The default location of the snippet library, next to the default config file.
+    pub fn default_snippets() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("mk").join("snippets"))
+    }
+
+    /// Loads the config at `path`, falling back to defaults if there is no such file.
+    pub fn load(path: &Path) -> anyhow::Result<Config> {
+        let contents = match std::fs::read_to_string(path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            result => {
+                result.with_context(|| format!("Unable to read config {}", path.display()))?
+            }
+        };
+        let config: Config = toml::from_str(&contents)
+            .with_context(|| format!("Invalid config {}", path.display()))?;
+        anyhow::ensure!(
+            config.shebang.is_none(),
+            "Invalid config {}, shebang only applies in a profile, use shebangs to match paths",
+            path.display()
+        );
+        Ok(config)
+    }
+
+    /// Loads the nearest project config in `dir` or any of its ancestors.
+    pub fn discover(dir: &Path) -> anyhow::Result<Option<Config>> {
+        dir.ancestors()
+            .map(|d| d.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+            .map(|path| Config::load(&path))
+            .transpose()
+    }
+
+    /// Layers `other` on top of this config, preferring its settings where both have one.
+    pub fn overlay(self, other: Config) -> Config {
+        let mut frontmatter = self.frontmatter;
+        frontmatter.extend(other.frontmatter);
+        let mut types = self.types;
+        types.extend(other.types);
+        let mut vars = self.vars;
+        vars.extend(other.vars);
+        let mut shebangs = self.shebangs;
+        shebangs.extend(other.shebangs);
+        let mut hooks = self.hooks;
+        hooks.extend(other.hooks);
+        let mut profile = self.profile;
+        profile.extend(other.profile);
+
+        Config {
+            kind: other.kind.or(self.kind),
+            mode: other.mode.or(self.mode),
+            shebang: other.shebang.or(self.shebang),
+            shebangs,
+            dotfiles: other.dotfiles.or(self.dotfiles),
+            executable: other.executable.or(self.executable),
+            executable_extensions: self
+                .executable_extensions
+                .overlay(other.executable_extensions),
+            directory_extensions: self
+                .directory_extensions
+                .overlay(other.directory_extensions),
+            gitkeep: other.gitkeep.or(self.gitkeep),
+            boilerplate: other.boilerplate.or(self.boilerplate),
+            git_add: other.git_add.or(self.git_add),
+            zoxide: other.zoxide.or(self.zoxide),
+            editor: other.editor.or(self.editor),
+            daily: other.daily.or(self.daily),
+            snippets: other.snippets.or(self.snippets),
+            frontmatter,
+            types,
+            vars,
+            hooks,
+            profile,
+        }
+    }
+
+    /// The line to start the new file at `path` with, from a profile's `shebang` or else the
+    /// first of `shebangs` whose pattern matches.
+    pub fn shebang_line(&self, path: &Path) -> anyhow::Result<Option<String>> {
+        let mut shebang = self.shebang.as_ref();
+        if shebang.is_none() {
+            let path = path.components().collect::<PathBuf>();
+            for (pattern, line) in &self.shebangs {
+                let glob = globset::Glob::new(pattern)
+                    .with_context(|| format!("Invalid shebang pattern {pattern}"))?;
+                if glob.compile_matcher().is_match(&path) {
+                    shebang = Some(line);
+                    break;
+                }
+            }
+        }
+        Ok(shebang.map(|shebang| match shebang.strip_prefix("#!") {
+            Some(_) => shebang.to_string(),
+            None => format!("#!/usr/bin/env {shebang}"),
+        }))
+    }
+
+    /// Whether this config or any of its profiles has hooks.
+    pub fn has_hooks(&self) -> bool {
+        !self.hooks.is_empty() || self.profile.values().any(Config::has_hooks)
+    }
+
+    /// This config with the hooks in it and its profiles removed, for configs that aren't
+    /// trusted to run commands.
+    pub fn without_hooks(mut self) -> Config {
+        self.hooks.clear();
+        self.profile = self
+            .profile
+            .into_iter()
+            .map(|(name, profile)| (name, profile.without_hooks()))
+            .collect();
+        self
+    }
+
+    /// Layers the named profile on top of this config.
+    pub fn with_profile(mut self, name: &str) -> anyhow::Result<Config> {
+        let profile = self
+            .profile
+            .remove(name)
+            .with_context(|| format!("Unknown profile {name}"))?;
+        Ok(self.overlay(profile))
+    }
+}
+
+/// Parses an octal mode like `755` or `2775`. Symbolic modes like `u+rwx` aren't supported.
+pub fn parse_mode(s: &str) -> anyhow::Result<u32> {
+    u32::from_str_radix(s, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .with_context(|| format!("Invalid mode {s}, expected an octal mode like 755"))
+}
+
+/// Reads a mode written as a string like `"755"`, or as a bare number whose digits are octal.
+fn octal_mode<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Mode {
+        Text(String),
+        Number(u64),
+    }
+
+    let s = match Mode::deserialize(deserializer)? {
+        Mode::Text(s) => s,
+        Mode::Number(n) => n.to_string(),
+    };
+    parse_mode(&s).map(Some).map_err(serde::de::Error::custom)
+}
+
+impl ExtensionOverrides {
+    fn overlay(mut self, other: ExtensionOverrides) -> ExtensionOverrides {
+        self.add.extend(other.add);
+        self.remove.extend(other.remove);
+        self
+    }
+
+    /// Applies the overrides to `builtin`.
+    pub fn apply<'a>(&'a self, builtin: &[&'a str]) -> Vec<&'a str> {
+        builtin
+            .iter()
+            .copied()
+            .chain(self.add.iter().map(String::as_str))
+            .filter(|ext| !self.remove.iter().any(|removed| removed == ext))
+            .collect()
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The `.editorconfig` properties that apply to a path, with lowercase names and values.
+pub type Properties = BTreeMap<String, String>;
+
+/// Collects the properties for `path` from the `.editorconfig` files in its ancestors, up to the
+/// first one marked `root = true`. Closer files and later sections win.
+pub fn properties(path: &Path) -> anyhow::Result<Properties> {
+    let mut files = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let file = dir.join(".editorconfig");
+        let contents = match std::fs::read_to_string(&file) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            result => result.with_context(|| format!("Unable to read {}", file.display()))?,
+        };
+        let (is_root, sections) = parse(&contents);
+        files.push((dir, sections));
+        if is_root {
+            break;
+        }
+    }
+
+    let mut properties = Properties::new();
+    for (dir, sections) in files.into_iter().rev() {
+        let relative = path
+            .strip_prefix(dir)
+            .expect("ancestor of path")
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        for (pattern, section) in sections {
+            if matches(&pattern, &relative)? {
+                properties.extend(section);
+            }
+        }
+    }
+    Ok(properties)
+}
+
+/// Splits an `.editorconfig` into whether it is the root and its sections in order.
+fn parse(contents: &str) -> (bool, Vec<(String, Properties)>) {
+    let mut is_root = false;
+    let mut sections = Vec::<(String, Properties)>::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(pattern) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((pattern.to_string(), Properties::new()));
+        } else if let Some((key, value)) = line.split_once('=') {
+            let (key, value) = (key.trim().to_lowercase(), value.trim().to_lowercase());
+            match sections.last_mut() {
+                Some((_, section)) => {
+                    section.insert(key, value);
+                }
+                None => is_root |= key == "root" && value == "true",
+            }
+        }
+    }
+    (is_root, sections)
+}
+
+/// Whether the section `pattern` covers `relative`, a `/`-separated path from the file's
+/// directory. Patterns without a slash match file names at any depth.
+fn matches(pattern: &str, relative: &str) -> anyhow::Result<bool> {
+    let pattern = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{pattern}"),
+    };
+    let glob = globset::GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("Invalid .editorconfig section [{pattern}]"))?;
+    Ok(glob.compile_matcher().is_match(relative))
+}
+
+/// Reformats generated `text` to follow `properties`. Indentation is converted from tabs, or
+/// from runs of `indent_size` spaces.
+pub fn format(text: &str, properties: &Properties) -> anyhow::Result<Vec<u8>> {
+    if properties.is_empty() {
+        return Ok(text.as_bytes().to_vec());
+    }
+    let get = |key: &str| properties.get(key).map(String::as_str);
+    let indent_size = match get("indent_size") {
+        Some("tab") | None => get("tab_width").and_then(|w| w.parse().ok()).unwrap_or(4),
+        Some(size) => size
+            .parse::<usize>()
+            .with_context(|| format!("Invalid indent_size {size} in .editorconfig"))?,
+    };
+    let newline = match get("end_of_line") {
+        Some("crlf") => "\r\n",
+        Some("cr") => "\r",
+        _ => "\n",
+    };
+
+    let mut lines = text
+        .lines()
+        .map(|line| {
+            let body = line.trim_start_matches([' ', '\t']);
+            let indent = &line[..line.len() - body.len()];
+            let width = indent
+                .chars()
+                .map(|c| if c == '\t' { indent_size } else { 1 })
+                .sum::<usize>();
+            let indent = match get("indent_style") {
+                Some("tab") if indent_size > 0 => {
+                    "\t".repeat(width / indent_size) + &" ".repeat(width % indent_size)
+                }
+                Some("space") => " ".repeat(width),
+                _ => indent.to_string(),
+            };
+            let body = match get("trim_trailing_whitespace") {
+                Some("true") => body.trim_end(),
+                _ => body,
+            };
+            indent + body
+        })
+        .collect::<Vec<_>>()
+        .join(newline);
+    let had_final_newline = text.ends_with('\n');
+    match get("insert_final_newline") {
+        Some("true") if !lines.is_empty() => lines += newline,
+        Some("false") => {}
+        _ if had_final_newline => lines += newline,
+        _ => {}
+    }
+
+    Ok(match get("charset") {
+        Some("utf-8-bom") => [&b"\xEF\xBB\xBF"[..], lines.as_bytes()].concat(),
+        Some("latin1") => lines
+            .chars()
+            .map(|c| u8::try_from(c).with_context(|| format!("{c} cannot be written as latin1")))
+            .collect::<anyhow::Result<_>>()?,
+        Some("utf-16be") => lines.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+        Some("utf-16le") => lines.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        _ => lines.into_bytes(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_properties_up_to_root() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir_all(dir.path().join("project/src"))?;
+        std::fs::write(dir.path().join(".editorconfig"), "[*]\ncharset = latin1\n")?;
+        std::fs::write(
+            dir.path().join("project/.editorconfig"),
+            "root = true\n[*]\nindent_style = space\nindent_size = 4\n\n[src/*.rs]\nindent_size = 2\n",
+        )?;
+        std::fs::write(
+            dir.path().join("project/src/.editorconfig"),
+            "[*.{rs,toml}]\nend_of_line = CRLF\n",
+        )?;
+
+        let properties = properties(&dir.path().join("project/src/main.rs"))?;
+
+        assert_eq!(
+            properties,
+            Properties::from([
+                ("end_of_line".to_string(), "crlf".to_string()),
+                ("indent_size".to_string(), "2".to_string()),
+                ("indent_style".to_string(), "space".to_string()),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn formats_text() -> anyhow::Result<()> {
+        let properties = Properties::from([
+            ("indent_style".to_string(), "space".to_string()),
+            ("indent_size".to_string(), "2".to_string()),
+            ("end_of_line".to_string(), "crlf".to_string()),
+            ("insert_final_newline".to_string(), "true".to_string()),
+            ("trim_trailing_whitespace".to_string(), "true".to_string()),
+        ]);
+
+        let formatted = format("fn main() {  \n\tbody();\n}", &properties)?;
+
+        assert_eq!(formatted, b"fn main() {\r\n  body();\r\n}\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn indents_with_tabs() -> anyhow::Result<()> {
+        let properties = Properties::from([("indent_style".to_string(), "tab".to_string())]);
+
+        assert_eq!(
+            format("a\n    b\n      c\n", &properties)?,
+            b"a\n\tb\n\t  c\n"
+        );
+        Ok(())
+    }
+}
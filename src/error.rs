@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Ways [`create`](crate::create) can fail, so callers can react to a kind of failure without
+/// parsing messages.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("Entry {} already exists", .0.display())]
+    AlreadyExists(PathBuf),
+
+    /// The entry already exists as the other kind. `has_entries` is set for a directory with
+    /// anything in it, and `inferred` when the wanted kind came from the name.
+    #[error("{} already exists as a {existing}, not a {wanted}", path.display())]
+    ExistsAsOtherKind {
+        path: PathBuf,
+        existing: &'static str,
+        wanted: &'static str,
+        has_entries: bool,
+        inferred: bool,
+    },
+
+    /// The entry can't be the kind it was asked or inferred to be.
+    #[error("{reason}: {}", path.display())]
+    TypeConflict { path: PathBuf, reason: &'static str },
+
+    #[error("Cannot make {}, {} is a file", path.display(), parent.display())]
+    ParentIsFile { path: PathBuf, parent: PathBuf },
+
+    #[error(
+        "Cannot make {}, {} already exists with different case",
+        path.display(),
+        existing.display()
+    )]
+    CaseCollision { path: PathBuf, existing: PathBuf },
+
+    #[error("Parent directory {} does not exist", .0.display())]
+    MissingParent(PathBuf),
+
+    #[error("Directory {} is not empty", .0.display())]
+    DirectoryNotEmpty(PathBuf),
+
+    #[error("Cannot create directory {} with stdin data", .0.display())]
+    StdinForDirectory(PathBuf),
+
+    #[error("Cannot combine a size with stdin data for {}", .0.display())]
+    StdinWithSize(PathBuf),
+
+    #[error(
+        "Contents of {} have {} checksum {actual}, expected {expected}",
+        path.display(),
+        expected.algorithm
+    )]
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: crate::checksum::Checksum,
+        actual: crate::checksum::Checksum,
+    },
+
+    #[error("Cannot combine copying a file with stdin data for {}", .0.display())]
+    StdinWithFrom(PathBuf),
+
+    #[error("Cannot copy {} onto itself", .0.display())]
+    CopyOntoItself(PathBuf),
+
+    #[error("Contents are longer than the limit of {0} bytes")]
+    ContentsTooLong(u64),
+
+    #[error(
+        "Not enough space for {}, it needs {needed} bytes but only {available} are free",
+        path.display()
+    )]
+    NotEnoughSpace {
+        path: PathBuf,
+        needed: u64,
+        available: u64,
+    },
+
+    #[error("{} leads outside of {}", path.display(), root.display())]
+    OutsideRoot { path: PathBuf, root: PathBuf },
+
+    #[error("{} is nested deeper than the limit of {max_depth} levels", path.display())]
+    TooDeep { path: PathBuf, max_depth: usize },
+
+    #[error("Component {name} is longer than {limit} bytes")]
+    NameTooLong { name: String, limit: usize },
+
+    #[error("Path {} is longer than {limit} bytes", path.display())]
+    PathTooLong { path: PathBuf, limit: usize },
+
+    #[error("{0} is a reserved name on Windows")]
+    ReservedName(String),
+
+    #[error(transparent)]
+    PermissionDenied(std::io::Error),
+
+    #[error(transparent)]
+    Io(std::io::Error),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        match e.kind() {
+            std::io::ErrorKind::PermissionDenied => Error::PermissionDenied(e),
+            _ => Error::Io(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separates_permission_errors() {
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+
+        assert!(matches!(Error::from(denied), Error::PermissionDenied(_)));
+        assert!(matches!(Error::from(missing), Error::Io(_)));
+    }
+}
@@ -0,0 +1,458 @@
+use crate::fs::Filesystem;
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+/// Expands a leading `~` to `home`, or `~user` to that user's home directory.
+pub fn tilde(path: &Path, home: Option<&Path>) -> anyhow::Result<PathBuf> {
+    let Some(rest) = path.to_str().and_then(|s| s.strip_prefix('~')) else {
+        return Ok(path.to_path_buf());
+    };
+    let (user, rest) = rest.split_at(rest.find(std::path::is_separator).unwrap_or(rest.len()));
+    let rest = rest.trim_start_matches(std::path::is_separator);
+
+    let home = if user.is_empty() {
+        home.context("Unable to find home directory")?.to_path_buf()
+    } else {
+        user_home(user).with_context(|| format!("Unknown user {user}"))?
+    };
+    Ok(if rest.is_empty() {
+        home
+    } else {
+        home.join(rest)
+    })
+}
+
+/// Expands `$VAR` and `${VAR}` using `lookup`, failing on undefined variables.
+pub fn env(path: &Path, lookup: impl Fn(&str) -> Option<String>) -> anyhow::Result<PathBuf> {
+    let s = path
+        .to_str()
+        .with_context(|| format!("Cannot expand variables in {}", path.display()))?;
+
+    let mut expanded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        rest = &rest[dollar + 1..];
+
+        let (name, after) = if let Some(braced) = rest.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .with_context(|| format!("Unclosed ${{ in {s}"))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let is_name_start = rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+            let end = if is_name_start {
+                rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len())
+            } else {
+                0
+            };
+            (&rest[..end], &rest[end..])
+        };
+
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        expanded.push_str(&lookup(name).with_context(|| format!("Undefined variable {name}"))?);
+        rest = after;
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded.into())
+}
+
+/// Resolves the `{n}`, `{uuid}`, and `{rand:LEN}` placeholders in `path`, retrying random names
+/// until one is unused in `root` on `fs`. `{{` and `}}` stand for literal braces, and braces
+/// around anything else are left alone.
+pub fn placeholders(fs: &impl Filesystem, root: &Path, path: &Path) -> anyhow::Result<PathBuf> {
+    let path = counter(fs, root, path)?;
+    let Some(s) = path.to_str() else {
+        return Ok(path);
+    };
+    let is_random = |name: &str| name == "uuid" || name == "rand" || name.starts_with("rand:");
+    if !placeholder_names(s).any(is_random) {
+        return Ok(unescape(s).into());
+    }
+
+    for _ in 0..100 {
+        let candidate = PathBuf::from(unescape(&random(s)?));
+        if !fs.exists(&root.join(&candidate)) {
+            return Ok(candidate);
+        }
+    }
+    anyhow::bail!("Unable to find an unused name for {}", unescape(s))
+}
+
+/// Finds the first placeholder in `s` at or after `from`, with the text between its braces.
+fn next_placeholder(s: &str, from: usize) -> Option<(std::ops::Range<usize>, &str)> {
+    let mut at = from;
+    while let Some(offset) = s[at..].find(['{', '}']) {
+        let start = at + offset;
+        let rest = &s[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            at = start + 2;
+            continue;
+        }
+        at = start + 1;
+        if rest.starts_with('}') {
+            continue;
+        }
+        let end = rest.find('}')?;
+        let name = &rest[1..end];
+        let known = match name.split_once(':') {
+            Some((kind, _)) => kind == "n" || kind == "rand",
+            None => matches!(name, "n" | "uuid" | "rand"),
+        };
+        if known {
+            return Some((start..start + end + 1, name));
+        }
+    }
+    None
+}
+
+fn placeholder_names(s: &str) -> impl Iterator<Item = &str> {
+    let mut at = 0;
+    std::iter::from_fn(move || {
+        let (range, name) = next_placeholder(s, at)?;
+        at = range.end;
+        Some(name)
+    })
+}
+
+/// Turns the `{{` and `}}` escapes back into single braces.
+fn unescape(s: &str) -> String {
+    s.replace("{{", "{").replace("}}", "}")
+}
+
+/// Replaces each `{uuid}` with a random version 4 UUID and each `{rand}` or `{rand:LEN}` with LEN
+/// random lowercase alphanumerics, 8 by default.
+fn random(s: &str) -> anyhow::Result<String> {
+    let mut expanded = s.to_string();
+    let mut at = 0;
+    while let Some((range, name)) = next_placeholder(&expanded, at) {
+        let value = match name {
+            "uuid" => uuid(),
+            "rand" => random_chars(8),
+            _ => match name.strip_prefix("rand:") {
+                Some(len) => random_chars(
+                    len.parse()
+                        .with_context(|| format!("Invalid random length in {s}"))?,
+                ),
+                None => {
+                    at = range.end;
+                    continue;
+                }
+            },
+        };
+        at = range.start + value.len();
+        expanded.replace_range(range, &value);
+    }
+    Ok(expanded)
+}
+
+fn random_chars(len: usize) -> String {
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    (0..len)
+        .map(|_| CHARS[fastrand::usize(..CHARS.len())] as char)
+        .collect()
+}
+
+fn uuid() -> String {
+    // Version 4 in the high nibble of the 7th byte, RFC 4122 variant in the top bits of the 9th.
+    let bits = fastrand::u128(..) & !(0xf << 76) & !(0x3 << 62) | (0x4 << 76) | (0x2 << 62);
+    let hex = format!("{bits:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// The directory, relative to the root, whose entries a `{n}` in `path` counts.
+pub fn counter_dir(path: &Path) -> Option<PathBuf> {
+    let s = path.to_str()?;
+    let (range, _) = counter_placeholder(s)?;
+    Some(match s[..range.start].rfind(std::path::is_separator) {
+        Some(sep) => unescape(&s[..sep]).into(),
+        None => PathBuf::new(),
+    })
+}
+
+fn counter_placeholder(s: &str) -> Option<(std::ops::Range<usize>, &str)> {
+    let mut at = 0;
+    loop {
+        let (range, name) = next_placeholder(s, at)?;
+        if name == "n" || name.starts_with("n:") {
+            return Some((range, name));
+        }
+        at = range.end;
+    }
+}
+
+/// Replaces a `{n}` or `{n:WIDTH}` placeholder with one more than the highest number already
+/// used by siblings in `root` sharing the text before it, zero-padded to WIDTH.
+fn counter(fs: &impl Filesystem, root: &Path, path: &Path) -> anyhow::Result<PathBuf> {
+    let Some(s) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+    let Some((range, name)) = counter_placeholder(s) else {
+        return Ok(path.to_path_buf());
+    };
+    let width = match name.strip_prefix("n:") {
+        None => 0,
+        Some(width) => width
+            .parse::<usize>()
+            .with_context(|| format!("Invalid counter width in {s}"))?,
+    };
+
+    let (dir, prefix) = match s[..range.start].rfind(std::path::is_separator) {
+        Some(sep) => (&s[..sep], &s[sep + 1..range.start]),
+        None => ("", &s[..range.start]),
+    };
+    let (dir, prefix) = (unescape(dir), unescape(prefix));
+    let siblings = match fs.read_dir(&root.join(dir)) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        result => result?,
+    };
+    let highest = siblings
+        .iter()
+        .filter_map(|name| {
+            let number = name.to_str()?.strip_prefix(prefix.as_str())?;
+            let digits = number
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(number.len());
+            number[..digits].parse::<u64>().ok()
+        })
+        .max()
+        .unwrap_or(0);
+
+    let number = format!("{:0width$}", highest + 1);
+    Ok(format!("{}{number}{}", &s[..range.start], &s[range.end..]).into())
+}
+
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<PathBuf> {
+    use std::ffi::{CStr, CString, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = CString::new(user).ok()?;
+    let mut buf = vec![0; 16 * 1024];
+    let mut passwd = std::mem::MaybeUninit::<libc::passwd>::uninit();
+    let mut result = std::ptr::null_mut();
+
+    // SAFETY: every pointer is valid for the duration of the call and `buf.len()` is the real
+    // size of the buffer.
+    let status = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            passwd.as_mut_ptr(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    if status != 0 || result.is_null() {
+        return None;
+    }
+
+    // SAFETY: getpwnam_r succeeded, so `passwd` is initialized and `pw_dir` points to a
+    // NUL-terminated string inside `buf`.
+    let dir = unsafe { CStr::from_ptr(passwd.assume_init().pw_dir) };
+    Some(PathBuf::from(OsStr::from_bytes(dir.to_bytes())))
+}
+
+#[cfg(not(unix))]
+fn user_home(_user: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::RealFs;
+
+    fn home() -> Option<&'static Path> {
+        Some(Path::new("/home/me"))
+    }
+
+    #[test]
+    fn expands_home() -> anyhow::Result<()> {
+        assert_eq!(tilde(Path::new("~"), home())?, Path::new("/home/me"));
+        assert_eq!(
+            tilde(Path::new("~/notes/today.md"), home())?,
+            Path::new("/home/me/notes/today.md")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_trailing_separator() -> anyhow::Result<()> {
+        assert_eq!(
+            tilde(Path::new("~/build/"), home())?.as_os_str(),
+            "/home/me/build/"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_other_paths_alone() -> anyhow::Result<()> {
+        assert_eq!(tilde(Path::new("foo/~"), home())?, Path::new("foo/~"));
+        assert_eq!(tilde(Path::new("./~"), home())?, Path::new("./~"));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn expands_user_home() -> anyhow::Result<()> {
+        assert_eq!(
+            tilde(Path::new("~root/foo"), home())?,
+            Path::new("/root/foo")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_unknown_user() -> anyhow::Result<()> {
+        assert!(tilde(Path::new("~no-such-user-here/foo"), home()).is_err());
+        Ok(())
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PROJECT" => Some("mk".to_string()),
+            "HOME" => Some("/home/me".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_variables() -> anyhow::Result<()> {
+        assert_eq!(
+            env(Path::new("$HOME/projects/${PROJECT}/notes.md"), lookup)?,
+            Path::new("/home/me/projects/mk/notes.md")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_lone_dollar() -> anyhow::Result<()> {
+        assert_eq!(
+            env(Path::new("costs/$5.txt"), lookup)?,
+            Path::new("costs/$5.txt")
+        );
+        assert_eq!(env(Path::new("a$"), lookup)?, Path::new("a$"));
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_undefined_variable() -> anyhow::Result<()> {
+        assert!(env(Path::new("$MISSING/foo"), lookup).is_err());
+        assert!(env(Path::new("${PROJECT/foo"), lookup).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn counts_past_highest_sibling() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir(dir.path().join("migrations"))?;
+        std::fs::write(dir.path().join("migrations/0002_init.sql"), "")?;
+        std::fs::write(dir.path().join("migrations/0006_add_posts.sql"), "")?;
+        std::fs::write(dir.path().join("migrations/README.md"), "")?;
+
+        assert_eq!(
+            counter(
+                &RealFs,
+                dir.path(),
+                Path::new("migrations/{n:04}_add_users.sql")
+            )?,
+            Path::new("migrations/0007_add_users.sql")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn counts_siblings_with_same_prefix() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("adr-3.md"), "")?;
+        std::fs::write(dir.path().join("note-9.md"), "")?;
+
+        assert_eq!(
+            counter(&RealFs, dir.path(), Path::new("adr-{n}.md"))?,
+            Path::new("adr-4.md")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn starts_counting_at_one() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert_eq!(
+            counter(&RealFs, dir.path(), Path::new("missing/{n:3}.md"))?,
+            Path::new("missing/001.md")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fills_random_placeholders() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        let path = placeholders(
+            &RealFs,
+            dir.path(),
+            Path::new("uploads/{uuid}/{rand:12}-{rand}.bin"),
+        )?;
+
+        let s = path.to_str().unwrap();
+        let (uuid, name) = s["uploads/".len()..].split_once('/').unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(uuid.as_bytes()[14], b'4');
+        assert_eq!(name.len(), "123456789012-12345678.bin".len());
+        assert!(!name.contains('{'));
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_paths_without_placeholders() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert_eq!(
+            placeholders(&RealFs, dir.path(), Path::new("{other}.txt"))?,
+            Path::new("{other}.txt")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_unknown_and_escaped_braces() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("{n}-1.md"), "")?;
+
+        assert_eq!(
+            placeholders(&RealFs, dir.path(), Path::new("{random_notes}.md"))?,
+            Path::new("{random_notes}.md")
+        );
+        assert_eq!(
+            placeholders(&RealFs, dir.path(), Path::new("{{uuid}}-{{n}}.md"))?,
+            Path::new("{uuid}-{n}.md")
+        );
+        assert_eq!(
+            placeholders(&RealFs, dir.path(), Path::new("{{n}}-{n}.md"))?,
+            Path::new("{n}-2.md")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_invalid_random_length() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(placeholders(&RealFs, dir.path(), Path::new("{rand:x}.txt")).is_err());
+        Ok(())
+    }
+}
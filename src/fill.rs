@@ -0,0 +1,110 @@
+use std::io::Write;
+
+const CHUNK: usize = 64 * 1024;
+
+/// Contents generated for a file made with `--size`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fill {
+    Zero,
+    Random,
+    /// Bytes repeated to fill the file, given like `0xAB` or `0xDEADBEEF`.
+    Pattern(Vec<u8>),
+}
+
+/// Parses `zero`, `random`, or a hex byte pattern like `0xAB`.
+pub fn parse(s: &str) -> anyhow::Result<Fill> {
+    match s.to_ascii_lowercase().as_str() {
+        "zero" => return Ok(Fill::Zero),
+        "random" => return Ok(Fill::Random),
+        _ => {}
+    }
+
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .filter(|hex| {
+            !hex.is_empty() && hex.len() % 2 == 0 && hex.bytes().all(|b| b.is_ascii_hexdigit())
+        })
+        .ok_or_else(|| invalid(s))?;
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid(s)))
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Fill::Pattern(bytes))
+}
+
+fn invalid(s: &str) -> anyhow::Error {
+    anyhow::anyhow!("Invalid fill {s}, expected zero, random, or a byte pattern like 0xAB")
+}
+
+/// Writes `size` bytes of `fill` to `out`.
+pub fn write(out: &mut impl Write, fill: &Fill, size: u64) -> std::io::Result<()> {
+    let mut chunk = match fill {
+        Fill::Zero | Fill::Random => vec![0; CHUNK],
+        // A whole number of repetitions, so every chunk starts where the pattern does.
+        Fill::Pattern(pattern) => pattern
+            .iter()
+            .cycle()
+            .take((CHUNK / pattern.len()).max(1) * pattern.len())
+            .copied()
+            .collect(),
+    };
+
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = remaining.min(chunk.len() as u64) as usize;
+        if *fill == Fill::Random {
+            fastrand::fill(&mut chunk[..len]);
+        }
+        out.write_all(&chunk[..len])?;
+        remaining -= len as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fills() -> anyhow::Result<()> {
+        assert_eq!(parse("zero")?, Fill::Zero);
+        assert_eq!(parse("Random")?, Fill::Random);
+        assert_eq!(parse("0xAB")?, Fill::Pattern(vec![0xab]));
+        assert_eq!(
+            parse("0xdeadBEEF")?,
+            Fill::Pattern(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_fills() {
+        assert!(parse("").is_err());
+        assert!(parse("0x").is_err());
+        assert!(parse("0xABC").is_err());
+        assert!(parse("0xZZ").is_err());
+        assert!(parse("0x+A").is_err());
+        assert!(parse("AB").is_err());
+    }
+
+    #[test]
+    fn repeats_pattern_across_chunks() -> anyhow::Result<()> {
+        let mut out = Vec::new();
+        write(&mut out, &Fill::Pattern(vec![1, 2, 3]), CHUNK as u64 + 5)?;
+
+        assert_eq!(out.len(), CHUNK + 5);
+        assert!(out.iter().enumerate().all(|(i, &b)| b == [1, 2, 3][i % 3]));
+        Ok(())
+    }
+
+    #[test]
+    fn writes_random_bytes() -> anyhow::Result<()> {
+        let mut out = Vec::new();
+        write(&mut out, &Fill::Random, 1000)?;
+
+        assert_eq!(out.len(), 1000);
+        assert!(out.iter().any(|&b| b != 0));
+        Ok(())
+    }
+}
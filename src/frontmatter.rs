@@ -0,0 +1,138 @@
+use std::path::Path;
+
+/// Syntax of the front matter block.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Yaml,
+    Toml,
+}
+
+/// Whether `path` is a Markdown file that front matter belongs in.
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ["md", "markdown", "mdx"].contains(&ext.to_lowercase().as_str()))
+}
+
+/// Turns a slugged file name like `hello-world.md` back into a title like `Hello World`.
+pub fn title(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    stem.split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().expect("words are not empty");
+            first.to_uppercase().chain(chars).collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A front matter block with `title` and `date` followed by `fields`, which can also replace
+/// the title or date.
+pub fn render(
+    format: Format,
+    title: &str,
+    date: &str,
+    fields: &std::collections::BTreeMap<String, toml::Value>,
+) -> String {
+    let date = date
+        .parse::<toml::value::Datetime>()
+        .map(toml::Value::Datetime)
+        .unwrap_or_else(|_| toml::Value::String(date.to_string()));
+    let mut entries = vec![
+        ("title".to_string(), toml::Value::String(title.to_string())),
+        ("date".to_string(), date),
+    ];
+    for (key, value) in fields {
+        match entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value.clone(),
+            None => entries.push((key.clone(), value.clone())),
+        }
+    }
+
+    let (fence, lines) = match format {
+        Format::Yaml => (
+            "---",
+            entries
+                .iter()
+                .map(|(key, value)| format!("{key}: {}", yaml(value)))
+                .collect::<Vec<_>>(),
+        ),
+        Format::Toml => (
+            "+++",
+            entries
+                .iter()
+                .map(|(key, value)| format!("{key} = {value}"))
+                .collect(),
+        ),
+    };
+    format!("{fence}\n{}\n{fence}\n\n", lines.join("\n"))
+}
+
+/// `value` as an inline YAML value.
+fn yaml(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => {
+            let mut quoted = String::from('"');
+            for c in s.chars() {
+                match c {
+                    '"' => quoted += "\\\"",
+                    '\\' => quoted += "\\\\",
+                    '\n' => quoted += "\\n",
+                    '\t' => quoted += "\\t",
+                    c if c.is_control() => quoted += &format!("\\u{:04x}", c as u32),
+                    c => quoted.push(c),
+                }
+            }
+            quoted + "\""
+        }
+        toml::Value::Array(items) => {
+            format!(
+                "[{}]",
+                items.iter().map(yaml).collect::<Vec<_>>().join(", ")
+            )
+        }
+        toml::Value::Table(table) => format!(
+            "{{{}}}",
+            table
+                .iter()
+                .map(|(key, value)| format!("{key}: {}", yaml(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_slugged_names() {
+        assert_eq!(
+            title(Path::new("posts/hello-world_again.md")),
+            "Hello World Again"
+        );
+    }
+
+    #[test]
+    fn renders_yaml_and_toml() -> anyhow::Result<()> {
+        let fields = toml::from_str("tags = [\"rust\"]\ndraft = true")?;
+
+        assert_eq!(
+            render(Format::Yaml, "Say \"Hi\"", "2024-05-06", &fields),
+            "---\ntitle: \"Say \\\"Hi\\\"\"\ndate: 2024-05-06\ndraft: true\ntags: [\"rust\"]\n---\n\n"
+        );
+        assert_eq!(
+            render(Format::Toml, "Hi", "2024-05-06", &fields),
+            "+++\ntitle = \"Hi\"\ndate = 2024-05-06\ndraft = true\ntags = [\"rust\"]\n+++\n\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,469 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// The filesystem operations [`create_in`](crate::create_in) makes entries with, so it can run
+/// against something other than the real disk.
+pub trait Filesystem {
+    type File: WriteFile;
+
+    /// Whether `path` exists, following symlinks.
+    fn exists(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    fn is_file(&self, path: &Path) -> bool {
+        self.exists(path) && !self.is_dir(path)
+    }
+
+    /// Names of the entries in the directory `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Creates a file at `path` for writing, failing if anything is already there.
+    fn create_new(&self, path: &Path) -> io::Result<Self::File>;
+
+    /// Opens the existing file at `path` for writing at its end.
+    fn open_append(&self, path: &Path) -> io::Result<Self::File>;
+    /// Fills the existing file at `to` with the contents of the file `from`, keeping the
+    /// permissions of `to`.
+    fn copy_contents(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Gives `to` the same permissions as `from`.
+    fn copy_permissions(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn set_executable(&self, path: &Path) -> io::Result<()>;
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Bytes available on the filesystem holding `path`, if known.
+    fn free_space(&self, path: &Path) -> io::Result<Option<u64>>;
+
+    /// Size in bytes of the file at `path`.
+    fn file_len(&self, path: &Path) -> io::Result<u64>;
+}
+
+/// A file opened by [`Filesystem::create_new`].
+pub trait WriteFile: io::Write {
+    fn set_len(&mut self, size: u64) -> io::Result<()>;
+
+    /// Sizes the file to `size` bytes backed by real blocks.
+    fn preallocate(&mut self, size: u64) -> io::Result<()>;
+    fn sync_all(&mut self) -> io::Result<()>;
+}
+
+/// The real filesystem.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFs;
+
+impl Filesystem for RealFs {
+    type File = std::fs::File;
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        std::fs::read_dir(path)?
+            .map(|entry| Ok(entry?.file_name()))
+            .collect()
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn create_new(&self, path: &Path) -> io::Result<std::fs::File> {
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+    }
+
+    fn open_append(&self, path: &Path) -> io::Result<std::fs::File> {
+        std::fs::OpenOptions::new().append(true).open(path)
+    }
+
+    fn copy_contents(&self, from: &Path, to: &Path) -> io::Result<()> {
+        crate::platform::copy_contents(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_dir_all(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    fn copy_permissions(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::set_permissions(to, std::fs::metadata(from)?.permissions())
+    }
+
+    #[cfg(unix)]
+    fn set_executable(&self, path: &Path) -> io::Result<()> {
+        let output = std::process::Command::new("chmod")
+            .arg("+x")
+            .arg(path)
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other("Unsuccessful in setting file executable"));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn set_executable(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn free_space(&self, path: &Path) -> io::Result<Option<u64>> {
+        crate::platform::free_space(path)
+    }
+
+    fn file_len(&self, path: &Path) -> io::Result<u64> {
+        Ok(path.metadata()?.len())
+    }
+}
+
+impl WriteFile for std::fs::File {
+    fn set_len(&mut self, size: u64) -> io::Result<()> {
+        std::fs::File::set_len(self, size)
+    }
+
+    fn preallocate(&mut self, size: u64) -> io::Result<()> {
+        crate::platform::preallocate(self, size)
+    }
+
+    fn sync_all(&mut self) -> io::Result<()> {
+        std::fs::File::sync_all(self)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Dir,
+    File { contents: Vec<u8>, executable: bool },
+}
+
+/// A filesystem kept in memory, starting with nothing but the root directory `/`. Clones share
+/// the same contents.
+#[derive(Clone, Debug)]
+pub struct MemoryFs {
+    nodes: Rc<RefCell<BTreeMap<PathBuf, Node>>>,
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        MemoryFs::new()
+    }
+}
+
+impl MemoryFs {
+    pub fn new() -> MemoryFs {
+        let nodes = BTreeMap::from([(PathBuf::from("/"), Node::Dir)]);
+        MemoryFs {
+            nodes: Rc::new(RefCell::new(nodes)),
+        }
+    }
+
+    /// The contents of the file at `path`.
+    pub fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        match self.nodes.borrow().get(&key(path)) {
+            Some(Node::File { contents, .. }) => Some(contents.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_executable(&self, path: &Path) -> bool {
+        matches!(
+            self.nodes.borrow().get(&key(path)),
+            Some(Node::File {
+                executable: true,
+                ..
+            })
+        )
+    }
+
+    fn file_mut<T>(
+        &self,
+        path: &Path,
+        f: impl FnOnce(&mut Vec<u8>, &mut bool) -> T,
+    ) -> io::Result<T> {
+        match self.nodes.borrow_mut().get_mut(&key(path)) {
+            Some(Node::File {
+                contents,
+                executable,
+            }) => Ok(f(contents, executable)),
+            Some(Node::Dir) => Err(io::ErrorKind::IsADirectory.into()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+/// Resolves `.` and `..` in `path` lexically, since there are no symlinks to follow.
+fn key(path: &Path) -> PathBuf {
+    let mut key = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => key.push(name),
+            Component::ParentDir => {
+                key.pop();
+            }
+            _ => {}
+        }
+    }
+    key
+}
+
+impl Filesystem for MemoryFs {
+    type File = MemoryFile;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.nodes.borrow().contains_key(&key(path))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.nodes.borrow().get(&key(path)), Some(Node::Dir))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        let dir = key(path);
+        if !self.is_dir(&dir) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        Ok(self
+            .nodes
+            .borrow()
+            .keys()
+            .filter(|p| p.parent() == Some(&dir))
+            .filter_map(|p| p.file_name().map(Into::into))
+            .collect())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let path = key(path);
+        let mut ancestors = path.ancestors().collect::<Vec<_>>();
+        ancestors.reverse();
+        let mut nodes = self.nodes.borrow_mut();
+        for dir in ancestors {
+            match nodes.get(dir) {
+                Some(Node::Dir) => {}
+                Some(Node::File { .. }) => return Err(io::ErrorKind::NotADirectory.into()),
+                None => {
+                    nodes.insert(dir.to_path_buf(), Node::Dir);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn create_new(&self, path: &Path) -> io::Result<MemoryFile> {
+        let path = key(path);
+        if self.exists(&path) {
+            return Err(io::ErrorKind::AlreadyExists.into());
+        }
+        if !path.parent().is_some_and(|parent| self.is_dir(parent)) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        self.nodes.borrow_mut().insert(
+            path.clone(),
+            Node::File {
+                contents: Vec::new(),
+                executable: false,
+            },
+        );
+        Ok(MemoryFile {
+            fs: self.clone(),
+            path,
+        })
+    }
+
+    fn open_append(&self, path: &Path) -> io::Result<MemoryFile> {
+        // Writes to a memory file always extend it.
+        self.file_mut(path, |_, _| ())?;
+        Ok(MemoryFile {
+            fs: self.clone(),
+            path: key(path),
+        })
+    }
+
+    fn copy_contents(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let source = self.file_mut(from, |contents, _| contents.clone())?;
+        self.file_mut(to, |contents, _| *contents = source)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.file_mut(path, |_, _| ())?;
+        self.nodes.borrow_mut().remove(&key(path));
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let path = key(path);
+        if !self.is_dir(&path) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        self.nodes.borrow_mut().retain(|p, _| !p.starts_with(&path));
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let (from, to) = (key(from), key(to));
+        let mut nodes = self.nodes.borrow_mut();
+        let moved = nodes
+            .keys()
+            .filter(|p| p.starts_with(&from))
+            .cloned()
+            .collect::<Vec<_>>();
+        if moved.is_empty() {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        nodes.retain(|p, _| !p.starts_with(&to));
+        for old in moved {
+            let node = nodes.remove(&old).expect("collected above");
+            let new = to.join(old.strip_prefix(&from).expect("collected above"));
+            nodes.insert(new, node);
+        }
+        Ok(())
+    }
+
+    fn copy_permissions(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let executable = self.is_executable(from);
+        self.file_mut(to, |_, e| *e = executable)
+    }
+
+    fn set_executable(&self, path: &Path) -> io::Result<()> {
+        self.file_mut(path, |_, executable| *executable = true)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let path = key(path);
+        match self.exists(&path) {
+            true => Ok(path),
+            false => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn free_space(&self, _path: &Path) -> io::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    fn file_len(&self, path: &Path) -> io::Result<u64> {
+        self.file_mut(path, |contents, _| contents.len() as u64)
+    }
+}
+
+/// A file being written in a [`MemoryFs`].
+#[derive(Debug)]
+pub struct MemoryFile {
+    fs: MemoryFs,
+    path: PathBuf,
+}
+
+impl io::Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.fs
+            .file_mut(&self.path, |contents, _| contents.extend_from_slice(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WriteFile for MemoryFile {
+    fn set_len(&mut self, size: u64) -> io::Result<()> {
+        let size = usize::try_from(size).map_err(io::Error::other)?;
+        self.fs
+            .file_mut(&self.path, |contents, _| contents.resize(size, 0))
+    }
+
+    fn preallocate(&mut self, size: u64) -> io::Result<()> {
+        self.set_len(size)
+    }
+
+    fn sync_all(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn writes_files_in_memory() -> io::Result<()> {
+        let fs = MemoryFs::new();
+        fs.create_dir_all(Path::new("/a/b"))?;
+
+        let mut file = fs.create_new(Path::new("/a/b/c.txt"))?;
+        file.write_all(b"contents")?;
+
+        assert!(fs.is_dir(Path::new("/a")));
+        assert!(fs.is_file(Path::new("/a/b/../b/c.txt")));
+        assert_eq!(
+            fs.read(Path::new("/a/b/c.txt")).as_deref(),
+            Some(&b"contents"[..])
+        );
+        assert_eq!(fs.read_dir(Path::new("/a/b"))?, ["c.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_existing_and_orphaned_files() -> io::Result<()> {
+        let fs = MemoryFs::new();
+        fs.create_new(Path::new("/a.txt"))?;
+
+        let exists = fs.create_new(Path::new("/a.txt")).unwrap_err();
+        assert_eq!(exists.kind(), io::ErrorKind::AlreadyExists);
+        let orphan = fs.create_new(Path::new("/missing/b.txt")).unwrap_err();
+        assert_eq!(orphan.kind(), io::ErrorKind::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn renames_over_files() -> io::Result<()> {
+        let fs = MemoryFs::new();
+        fs.create_new(Path::new("/old.txt"))?.write_all(b"old")?;
+        fs.create_new(Path::new("/new.txt"))?.write_all(b"new")?;
+        fs.set_executable(Path::new("/old.txt"))?;
+
+        fs.copy_permissions(Path::new("/old.txt"), Path::new("/new.txt"))?;
+        fs.rename(Path::new("/new.txt"), Path::new("/old.txt"))?;
+
+        assert!(!fs.exists(Path::new("/new.txt")));
+        assert_eq!(fs.read(Path::new("/old.txt")).as_deref(), Some(&b"new"[..]));
+        assert!(fs.is_executable(Path::new("/old.txt")));
+        Ok(())
+    }
+
+    #[test]
+    fn removes_directory_trees() -> io::Result<()> {
+        let fs = MemoryFs::new();
+        fs.create_dir_all(Path::new("/a/b"))?;
+        fs.create_new(Path::new("/a/b/c.txt"))?;
+
+        fs.remove_dir_all(Path::new("/a"))?;
+
+        assert!(!fs.exists(Path::new("/a/b/c.txt")));
+        assert!(fs.is_dir(Path::new("/")));
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Walks up from `dir` to the nearest directory containing a `.git` entry.
+pub fn find_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|p| p.join(".git").exists())
+}
+
+pub fn init(dir: &Path) -> anyhow::Result<()> {
+    git(dir, ["init", "--quiet"]).with_context(|| {
+        format!(
+            "Unsuccessful in initializing repository in {}",
+            dir.display()
+        )
+    })?;
+    Ok(())
+}
+
+pub fn add(path: &Path) -> anyhow::Result<()> {
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent().expect("joined with root")
+    };
+
+    git(dir, ["add".as_ref(), "--".as_ref(), path.as_os_str()])
+        .with_context(|| format!("Unsuccessful in staging {}", path.display()))?;
+    Ok(())
+}
+
+/// The existing `paths` that are ignored by the `.gitignore` rules of the work tree holding them,
+/// asking git once per work tree. Paths outside a work tree, or where git can't run, count as not
+/// ignored.
+pub fn ignored(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut by_root = BTreeMap::<&Path, Vec<&PathBuf>>::new();
+    for path in paths {
+        if let Some(root) = path.parent().and_then(find_root) {
+            by_root.entry(root).or_default().push(path);
+        }
+    }
+    by_root
+        .into_iter()
+        .flat_map(|(root, paths)| check_ignore(root, &paths).unwrap_or_default())
+        .collect()
+}
+
+/// Runs `git check-ignore` in `root` with `paths` on stdin, returning the ignored ones.
+fn check_ignore(root: &Path, paths: &[&PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    use std::io::Write;
+
+    let mut child = std::process::Command::new("git")
+        .args(["check-ignore", "--stdin", "-z"])
+        .current_dir(root)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("piped");
+    let input = paths
+        .iter()
+        .flat_map(|path| [path.as_os_str().as_encoded_bytes(), b"\0"])
+        .flatten()
+        .copied()
+        .collect::<Vec<u8>>();
+    // Written from another thread so a large batch can't fill both pipes and deadlock.
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    writer.join().expect("writing paths doesn't panic")?;
+    // Exits with 1 when none of the paths are ignored.
+    anyhow::ensure!(
+        matches!(output.status.code(), Some(0 | 1)),
+        "git check-ignore failed"
+    );
+    // Paths are echoed as given, so they map back to the ones asked about.
+    let ignored = output
+        .stdout
+        .split(|&b| b == 0)
+        .collect::<std::collections::HashSet<_>>();
+    Ok(paths
+        .iter()
+        .filter(|path| ignored.contains(path.as_os_str().as_encoded_bytes()))
+        .map(|path| path.to_path_buf())
+        .collect())
+}
+
+/// Runs git in `dir`, returning its stdout or failing with its stderr.
+fn git<S: AsRef<std::ffi::OsStr>>(
+    dir: &Path,
+    args: impl IntoIterator<Item = S>,
+) -> anyhow::Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()?;
+    anyhow::ensure!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
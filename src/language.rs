@@ -0,0 +1,265 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// How to make a file in some language with `--type`. Config entries are layered over the
+/// built-in ones of the same name, field by field.
+#[derive(Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Language {
+    /// Extension given to paths that don't already end in it, like `rs`.
+    pub extension: Option<String>,
+
+    /// Other names `--type` accepts, like `rs` for `rust`.
+    pub aliases: Vec<String>,
+
+    /// Template to fill new files from.
+    pub template: Option<PathBuf>,
+
+    /// Line to start new files with when there is no template, like `#!/usr/bin/env bash`.
+    pub shebang: Option<String>,
+
+    /// Whether new files are made executable.
+    pub executable: Option<bool>,
+}
+
+/// Built-in languages as name, aliases, extension, and shebang. Those with a shebang are
+/// executable.
+const BUILTIN: &[(&str, &[&str], &str, Option<&str>)] = &[
+    ("bash", &["sh", "shell"], "sh", Some("#!/usr/bin/env bash")),
+    ("c", &[], "c", None),
+    ("cpp", &["c++", "cxx"], "cpp", None),
+    ("css", &[], "css", None),
+    ("fish", &[], "fish", Some("#!/usr/bin/env fish")),
+    ("go", &["golang"], "go", None),
+    ("html", &[], "html", None),
+    ("java", &[], "java", None),
+    ("javascript", &["js", "node"], "js", None),
+    ("json", &[], "json", None),
+    ("markdown", &["md"], "md", None),
+    ("python", &["py"], "py", None),
+    ("ruby", &["rb"], "rb", None),
+    ("rust", &["rs"], "rs", None),
+    ("toml", &[], "toml", None),
+    ("typescript", &["ts"], "ts", None),
+    ("yaml", &["yml"], "yaml", None),
+    ("zsh", &[], "zsh", Some("#!/usr/bin/env zsh")),
+];
+
+/// The language `name` or one of its aliases refers to, from the built-in registry layered with
+/// the `configured` ones.
+pub fn find<'a>(
+    name: &str,
+    configured: impl IntoIterator<Item = (&'a String, &'a Language)>,
+) -> Option<Language> {
+    let name = name.to_lowercase();
+    let builtin = BUILTIN
+        .iter()
+        .find(|(n, aliases, ..)| *n == name || aliases.contains(&name.as_str()));
+    let configured = configured
+        .into_iter()
+        .find(|(n, language)| {
+            **n == name
+                || language.aliases.contains(&name)
+                || builtin.is_some_and(|(builtin, ..)| *n == builtin)
+        })
+        .map(|(_, language)| language.clone());
+
+    let builtin = builtin.map(|(_, aliases, extension, shebang)| Language {
+        extension: Some(extension.to_string()),
+        aliases: aliases.iter().map(|a| a.to_string()).collect(),
+        template: None,
+        shebang: shebang.map(str::to_string),
+        executable: Some(shebang.is_some()),
+    });
+    match (builtin, configured) {
+        (Some(builtin), Some(configured)) => Some(Language {
+            extension: configured.extension.or(builtin.extension),
+            aliases: [builtin.aliases, configured.aliases].concat(),
+            template: configured.template.or(builtin.template),
+            shebang: configured.shebang.or(builtin.shebang),
+            executable: configured.executable.or(builtin.executable),
+        }),
+        (builtin, configured) => configured.or(builtin),
+    }
+}
+
+/// Names of the built-in languages, for listing them.
+pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+    BUILTIN.iter().map(|(name, ..)| *name)
+}
+
+/// Minimal starting contents for a new source file at `path`, picked by its extension. Rust, Go,
+/// and C files only get a `main` where a program's entry point goes.
+pub fn boilerplate(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    let is_bin = path
+        .parent()
+        .is_some_and(|parent| parent.file_name().is_some_and(|dir| dir == "bin"));
+    Some(match path.extension()?.to_str()? {
+        "rs" if is_bin || ["main.rs", "build.rs"].contains(&name) => "fn main() {}\n",
+        "go" if name == "main.go" => "package main\n\nfunc main() {\n}\n",
+        "c" if name == "main.c" => "int main(void) {\n    return 0;\n}\n",
+        "py" => "def main():\n    pass\n\n\nif __name__ == \"__main__\":\n    main()\n",
+        "sh" | "bash" => "#!/usr/bin/env bash\nset -euo pipefail\n\n",
+        "zsh" => "#!/usr/bin/env zsh\nset -euo pipefail\n\n",
+        _ => return None,
+    })
+}
+
+/// Where the conventional test file for the source file `path` goes: `tests/parser.rs` and
+/// `tests/test_parser.py` beside the enclosing `src` directory (or in the file's own directory
+/// without one), and `parser_test.go` next to Go files.
+pub fn test_path(path: &Path) -> Option<PathBuf> {
+    let stem = path.file_stem()?.to_str()?;
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let project = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.file_name().is_some_and(|name| name == "src"))
+        .map_or(parent, |src| src.parent().unwrap_or(Path::new("")));
+    Some(match path.extension()?.to_str()? {
+        "rs" => project.join("tests").join(format!("{stem}.rs")),
+        "py" => project.join("tests").join(format!("test_{stem}.py")),
+        "go" => parent.join(format!("{stem}_test.go")),
+        _ => return None,
+    })
+}
+
+/// Starting contents for the test file at `path`, as named by [`test_path`], with a single empty
+/// test named after the file.
+pub fn test_boilerplate(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    Some(match path.extension()?.to_str()? {
+        "rs" => format!("#[test]\nfn {}() {{}}\n", identifier(stem)),
+        "py" => format!("def {}():\n    pass\n", identifier(stem)),
+        "go" => {
+            let package = path
+                .parent()
+                .and_then(Path::file_name)
+                .and_then(|dir| dir.to_str())
+                .map_or("main".to_string(), identifier);
+            let name = stem.strip_suffix("_test").unwrap_or(stem);
+            let name = identifier(name)
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or(String::new(), |first| {
+                        first.to_uppercase().chain(chars).collect()
+                    })
+                })
+                .collect::<String>();
+            format!(
+                "package {package}\n\nimport \"testing\"\n\nfunc Test{name}(t *testing.T) {{\n}}\n"
+            )
+        }
+        _ => return None,
+    })
+}
+
+/// `name` with anything that can't go in an identifier replaced by underscores.
+fn identifier(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+impl Language {
+    /// `path` with this language's extension added, unless it already has it.
+    pub fn path(&self, path: &Path) -> PathBuf {
+        match &self.extension {
+            Some(extension) if path.extension().is_none_or(|ext| *ext != **extension) => {
+                let mut path = path.as_os_str().to_owned();
+                path.push(".");
+                path.push(extension);
+                path.into()
+            }
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn finds_languages_by_alias_with_config_on_top() -> anyhow::Result<()> {
+        let configured: BTreeMap<String, Language> = toml::from_str(
+            r#"
+            rust = { template = "templates/lib.rs" }
+            nix = { extension = "nix", aliases = ["flake"] }
+            "#,
+        )?;
+
+        let rust = find("RS", &configured).expect("built in");
+        assert_eq!(rust.extension.as_deref(), Some("rs"));
+        assert_eq!(rust.template, Some(PathBuf::from("templates/lib.rs")));
+        assert_eq!(rust.executable, Some(false));
+        assert_eq!(
+            find("flake", &configured).and_then(|l| l.extension),
+            Some("nix".to_string())
+        );
+        assert_eq!(
+            find("sh", &configured).and_then(|l| l.executable),
+            Some(true)
+        );
+        assert!(find("cobol", &configured).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn picks_boilerplate_by_extension() {
+        assert_eq!(
+            boilerplate(Path::new("src/bin/tool.rs")),
+            Some("fn main() {}\n")
+        );
+        assert_eq!(boilerplate(Path::new("src/parser.rs")), None);
+        assert!(boilerplate(Path::new("deploy.sh")).is_some_and(|b| b.contains("pipefail")));
+        assert_eq!(boilerplate(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn derives_test_companions() {
+        let rust = test_path(Path::new("crates/lexer/src/token/kind.rs")).expect("rust");
+        assert_eq!(rust, Path::new("crates/lexer/tests/kind.rs"));
+        assert_eq!(
+            test_boilerplate(&rust).as_deref(),
+            Some("#[test]\nfn kind() {}\n")
+        );
+
+        let python = test_path(Path::new("parser.py")).expect("python");
+        assert_eq!(python, Path::new("tests/test_parser.py"));
+        assert_eq!(
+            test_boilerplate(&python).as_deref(),
+            Some("def test_parser():\n    pass\n")
+        );
+
+        let go = test_path(Path::new("cmd/http-server/route_table.go")).expect("go");
+        assert_eq!(go, Path::new("cmd/http-server/route_table_test.go"));
+        assert!(
+            test_boilerplate(&go).is_some_and(|b| b.starts_with("package http_server\n")
+                && b.contains("func TestRouteTable(t *testing.T)"))
+        );
+
+        assert_eq!(test_path(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn adds_missing_extension() {
+        let rust = find("rust", []).expect("built in");
+
+        assert_eq!(
+            rust.path(Path::new("src/parser")),
+            Path::new("src/parser.rs")
+        );
+        assert_eq!(rust.path(Path::new("src/lib.rs")), Path::new("src/lib.rs"));
+        assert_eq!(rust.path(Path::new("v1.2")), Path::new("v1.2.rs"));
+    }
+}
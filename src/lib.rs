@@ -0,0 +1,1141 @@
+//! Making files and directories the way the `mk` command does, for tools that want the same
+//! behavior without spawning it.
+//!
+//! ```no_run
+//! let request = mk::CreateRequest::new("notes/idea.md").gitkeep(true);
+//! let entry = mk::create(".".as_ref(), &request, &[][..], &mut Vec::new())?;
+//! assert!(entry.is_file);
+//! # anyhow::Ok(())
+//! ```
+
+pub mod checksum;
+pub mod config;
+pub mod editorconfig;
+mod error;
+pub mod expand;
+pub mod fill;
+pub mod frontmatter;
+pub mod fs;
+pub mod git;
+pub mod language;
+pub mod names;
+#[cfg(feature = "async")]
+mod nonblocking;
+pub mod platform;
+pub mod template;
+pub mod validate;
+
+use anyhow::Context;
+use fs::WriteFile;
+use std::path::{Path, PathBuf};
+
+pub use config::{Config, Kind};
+pub use error::{Error, Result};
+pub use fill::Fill;
+pub use fs::{Filesystem, MemoryFs, RealFs};
+pub use names::Normalization;
+#[cfg(feature = "async")]
+pub use nonblocking::create_async;
+
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "exe", "bat", "cmd", "com", "ps1", "vbs", "msi", "scr", // Windows
+    "sh", "bash", "zsh", "ksh", "fish", "nu", // Shells
+    "run", "bin", "cgi", "py", "pl", "rb", "php", "tcl", // Unix-like
+    "jar", "appimage", "apk", "wasm", "pyz", // Cross-platform
+];
+
+/// Patterns for extensions that are still inferred as directories, like `rollout.d`, `v1.2`, or
+/// `backup.2024`.
+const DIRECTORY_EXTENSIONS: &[&str] = &["d", "[0-9]*"];
+
+/// Buffer size for writing contents when none is given.
+const DEFAULT_BUFFER_SIZE: u64 = 1024 * 1024;
+
+/// What to make and how, built up from [`CreateRequest::new`] and passed to [`create`].
+#[derive(Clone, Debug)]
+pub struct CreateRequest {
+    path: PathBuf,
+    kind: Option<Kind>,
+    overwrite: bool,
+    append: bool,
+    recursive: bool,
+    overwrite_parents: bool,
+    parents: bool,
+    executable: bool,
+    auto_exec: bool,
+    gitkeep: bool,
+    git_init: bool,
+    size: Option<u64>,
+    fill: Option<Fill>,
+    preallocate: bool,
+    buffer_size: u64,
+    max_bytes: Option<u64>,
+    verify: Option<checksum::Checksum>,
+    from: Option<PathBuf>,
+    slug: bool,
+    sanitize: bool,
+    normalize: Option<Normalization>,
+    restrict: bool,
+    max_depth: Option<usize>,
+    portable_names: bool,
+    check_case: bool,
+    force: bool,
+    config: Config,
+    resolved: bool,
+}
+
+impl CreateRequest {
+    /// A request to make `path`, relative to the root given to [`create`], with its kind inferred
+    /// from its name.
+    pub fn new(path: impl Into<PathBuf>) -> CreateRequest {
+        CreateRequest {
+            path: path.into(),
+            kind: None,
+            overwrite: false,
+            append: false,
+            recursive: false,
+            overwrite_parents: false,
+            parents: true,
+            executable: false,
+            auto_exec: true,
+            gitkeep: false,
+            git_init: false,
+            size: None,
+            fill: None,
+            preallocate: false,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            max_bytes: None,
+            verify: None,
+            from: None,
+            slug: false,
+            sanitize: false,
+            normalize: None,
+            restrict: false,
+            max_depth: None,
+            portable_names: false,
+            check_case: false,
+            force: false,
+            config: Config::default(),
+            resolved: false,
+        }
+    }
+
+    /// The path to make, relative to the root.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether this makes a file rather than a directory, from the kind asked for or else the
+    /// name. Placeholders count as written, so ask a request from [`resolve`].
+    pub fn is_file(&self) -> Result<bool> {
+        choose_kind(&self.path, self).map(|(is_file, _)| is_file)
+    }
+
+    /// Makes a file or directory regardless of what the name suggests.
+    pub fn kind(mut self, kind: impl Into<Option<Kind>>) -> Self {
+        self.kind = kind.into();
+        self
+    }
+
+    /// Replaces an existing entry, even one of the other kind.
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Adds the contents to the end of an existing file instead of failing. Missing files are
+    /// made as usual.
+    pub fn append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Lets an overwrite replace a non-empty directory with a file.
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Replaces existing files that are in the way of parent directories.
+    pub fn overwrite_parents(mut self, overwrite_parents: bool) -> Self {
+        self.overwrite_parents = overwrite_parents;
+        self
+    }
+
+    /// Creates missing parent directories. On by default.
+    pub fn parents(mut self, parents: bool) -> Self {
+        self.parents = parents;
+        self
+    }
+
+    /// Makes the file executable.
+    pub fn executable(mut self, executable: bool) -> Self {
+        self.executable = executable;
+        self
+    }
+
+    /// Infers that files are executable from their extension. On by default.
+    pub fn auto_exec(mut self, auto_exec: bool) -> Self {
+        self.auto_exec = auto_exec;
+        self
+    }
+
+    /// Writes an empty .gitkeep into every created directory.
+    pub fn gitkeep(mut self, gitkeep: bool) -> Self {
+        self.gitkeep = gitkeep;
+        self
+    }
+
+    /// Initializes a git repository inside the created directory.
+    pub fn git_init(mut self, git_init: bool) -> Self {
+        self.git_init = git_init;
+        self
+    }
+
+    /// Makes a file of `size` bytes instead of writing contents, sparse unless filled or
+    /// preallocated.
+    pub fn size(mut self, size: impl Into<Option<u64>>) -> Self {
+        self.size = size.into();
+        self
+    }
+
+    /// Fills a sized file with generated contents.
+    pub fn fill(mut self, fill: impl Into<Option<Fill>>) -> Self {
+        self.fill = fill.into();
+        self
+    }
+
+    /// Reserves real blocks for a sized file.
+    pub fn preallocate(mut self, preallocate: bool) -> Self {
+        self.preallocate = preallocate;
+        self
+    }
+
+    /// Buffers writes of contents in chunks of `buffer_size` bytes.
+    pub fn buffer_size(mut self, buffer_size: u64) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Fails, removing the partial file, if the contents are longer than `max_bytes`.
+    pub fn max_bytes(mut self, max_bytes: impl Into<Option<u64>>) -> Self {
+        self.max_bytes = max_bytes.into();
+        self
+    }
+
+    /// Fails, leaving nothing behind, unless the contents have the `verify` checksum. Contents
+    /// are staged beside the file and only moved into place once they match.
+    pub fn verify(mut self, verify: impl Into<Option<checksum::Checksum>>) -> Self {
+        self.verify = verify.into();
+        self
+    }
+
+    /// Copies the contents of the existing file `from`, relative to the root, instead of writing
+    /// any. Copy-on-write filesystems share its blocks, so even huge seeds copy instantly.
+    pub fn from(mut self, from: impl Into<Option<PathBuf>>) -> Self {
+        self.from = from.into();
+        self
+    }
+
+    /// Turns the final component into a lowercase, dash-separated slug.
+    pub fn slug(mut self, slug: bool) -> Self {
+        self.slug = slug;
+        self
+    }
+
+    /// Replaces characters that are invalid in file names on any platform.
+    pub fn sanitize(mut self, sanitize: bool) -> Self {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// Converts the path to a consistent Unicode normalization form.
+    pub fn normalize(mut self, normalize: impl Into<Option<Normalization>>) -> Self {
+        self.normalize = normalize.into();
+        self
+    }
+
+    /// Refuses paths that lead outside the root.
+    pub fn restrict(mut self, restrict: bool) -> Self {
+        self.restrict = restrict;
+        self
+    }
+
+    /// Refuses paths nested more than `max_depth` levels deep.
+    pub fn max_depth(mut self, max_depth: impl Into<Option<usize>>) -> Self {
+        self.max_depth = max_depth.into();
+        self
+    }
+
+    /// Rejects names that are reserved on Windows, even on other platforms.
+    pub fn portable_names(mut self, portable_names: bool) -> Self {
+        self.portable_names = portable_names;
+        self
+    }
+
+    /// Refuses names that differ only in case from an existing entry, as macOS and Windows
+    /// always do.
+    pub fn check_case(mut self, check_case: bool) -> Self {
+        self.check_case = check_case;
+        self
+    }
+
+    /// Warns instead of failing on reserved names.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Infers kinds and executables using `config` instead of the built-in defaults.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+/// An entry made by [`create`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Where the entry was made, joined with the root.
+    pub path: PathBuf,
+    /// The requested path after placeholders, slugs, and other renaming.
+    pub resolved: PathBuf,
+    pub is_file: bool,
+    /// A component Windows reserves as a device, made anyway because of `force`.
+    pub reserved_name: Option<String>,
+    /// Bytes written into the file from contents, a fill, or a copy. Sparse and preallocated
+    /// sizes write nothing, so they aren't counted.
+    pub written: u64,
+}
+
+/// Makes the entry `request` asks for under `root`, writing `contents` into it if it is a file.
+///
+/// Every entry that did not exist before, including parents and .gitkeep files, is recorded in
+/// `created` as it is made, so callers can undo a failed batch.
+pub fn create(
+    root: &Path,
+    request: &CreateRequest,
+    contents: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> Result<Entry> {
+    create_in(&RealFs, root, request, contents, created)
+}
+
+/// Fills in the placeholders in the path of `request` and renames it as asked, returning a
+/// request for exactly the path [`create_in`] would make. Making the entry with it keeps the
+/// name seen here, where resolving again could draw another random name or count past an entry
+/// made in between.
+pub fn resolve(
+    fs: &impl Filesystem,
+    root: &Path,
+    request: &CreateRequest,
+) -> Result<CreateRequest> {
+    Ok(CreateRequest {
+        path: resolve_path(fs, root, request)?,
+        resolved: true,
+        ..request.clone()
+    })
+}
+
+fn resolve_path(fs: &impl Filesystem, root: &Path, request: &CreateRequest) -> Result<PathBuf> {
+    if request.resolved {
+        return Ok(request.path.clone());
+    }
+    let mut resolved = expand::placeholders(fs, root, &request.path)?;
+    if request.slug {
+        resolved = names::slug(&resolved);
+    }
+    if request.sanitize {
+        resolved = names::sanitize(&resolved);
+    }
+    if let Some(form) = request.normalize {
+        resolved = names::normalize(&resolved, form);
+    }
+    if resolved != request.path {
+        tracing::debug!(resolved = %resolved.display(), "renamed");
+    }
+    Ok(resolved)
+}
+
+/// Like [`create`], but makes the entry on `fs` instead of the real filesystem.
+pub fn create_in(
+    fs: &impl Filesystem,
+    root: &Path,
+    request: &CreateRequest,
+    contents: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> Result<Entry> {
+    let _span = tracing::debug_span!("create", path = %request.path.display()).entered();
+    let resolved = resolve_path(fs, root, request)?;
+    let target = &resolved;
+    let path = platform::long_path(&root.join(target));
+
+    if request.restrict {
+        ensure_within(fs, root, target)?;
+    }
+    platform::check_limits(&path)?;
+    if let Some(max_depth) = request.max_depth
+        && platform::depth(target) > max_depth
+    {
+        return Err(Error::TooDeep {
+            path: target.clone(),
+            max_depth,
+        });
+    }
+
+    let reserved_name = names::reserved_component(target)
+        .filter(|_| cfg!(windows) || request.portable_names)
+        .map(str::to_string);
+    if let Some(name) = &reserved_name
+        && !request.force
+    {
+        return Err(Error::ReservedName(name.clone()));
+    }
+
+    if (cfg!(any(windows, target_os = "macos")) || request.check_case)
+        && let Some(existing) = case_collision(fs, root, target)?
+    {
+        return Err(Error::CaseCollision {
+            path: target.clone(),
+            existing,
+        });
+    }
+
+    let (is_file, why) = choose_kind(target, request)?;
+    tracing::debug!(is_file, why, "chose kind");
+
+    let parent = path.parent().expect("joined with root");
+    let existing_ancestor = parent
+        .ancestors()
+        .find(|p| fs.exists(p))
+        .expect("root exists");
+    if !fs.is_dir(existing_ancestor) && !request.overwrite_parents {
+        return Err(Error::ParentIsFile {
+            path: target.clone(),
+            parent: existing_ancestor.to_path_buf(),
+        });
+    }
+
+    let appends = request.append && is_file && fs.is_file(&path);
+    if !request.overwrite && !appends && fs.exists(&path) {
+        if fs.is_dir(&path) == is_file {
+            return Err(exists_as_other_kind(
+                fs,
+                &path,
+                target,
+                is_file,
+                request.kind,
+            )?);
+        }
+        return Err(Error::AlreadyExists(target.clone()));
+    }
+
+    if is_file && request.git_init {
+        return Err(Error::TypeConflict {
+            path: target.clone(),
+            reason: "Cannot initialize a git repository in a file",
+        });
+    }
+    if !is_file && request.size.is_some() {
+        return Err(Error::TypeConflict {
+            path: target.clone(),
+            reason: "Directory cannot have a size",
+        });
+    }
+
+    if let Some(size) = request.size
+        && (request.fill.is_some() || request.preallocate)
+    {
+        ensure_free_space(fs, existing_ancestor, target, size)?;
+    }
+    // A missing source is reported when copying.
+    if is_file
+        && let Some(from) = &request.from
+        && let Ok(len) = fs.file_len(&root.join(from))
+    {
+        ensure_free_space(fs, existing_ancestor, target, len)?;
+    }
+
+    let replaces_directory = is_file && fs.is_dir(&path);
+    let replaces_file = !is_file && fs.exists(&path) && !fs.is_dir(&path);
+    if replaces_directory && !request.recursive && !fs.read_dir(&path)?.is_empty() {
+        return Err(Error::DirectoryNotEmpty(target.clone()));
+    }
+
+    if !request.parents && !fs.is_dir(parent) {
+        return Err(Error::MissingParent(parent.to_path_buf()));
+    }
+
+    if !fs.is_dir(existing_ancestor) {
+        tracing::debug!(file = %existing_ancestor.display(), "removing file in the way of parents");
+        fs.remove_file(existing_ancestor)?;
+    }
+    if replaces_directory {
+        tracing::debug!("removing directory to replace");
+        fs.remove_dir_all(&path)?;
+    }
+    if replaces_file {
+        tracing::debug!("removing file to replace");
+        fs.remove_file(&path)?;
+    }
+
+    let from = request.from.as_ref().map(|from| root.join(from));
+    let mut written = 0;
+    if is_file {
+        written = create_file(fs, &path, request, from.as_deref(), contents, created)?;
+    } else {
+        create_directory(fs, &path, request, contents, created)?;
+    }
+
+    Ok(Entry {
+        path,
+        resolved,
+        is_file,
+        reserved_name,
+        written,
+    })
+}
+
+/// Whether `request` makes `target` a file, and why.
+fn choose_kind(target: &Path, request: &CreateRequest) -> Result<(bool, &'static str)> {
+    let has_trailing_separator = target
+        .as_os_str()
+        .as_encoded_bytes()
+        .last()
+        .is_some_and(|&b| std::path::is_separator(b.into()));
+
+    Ok(match request.kind {
+        None if has_trailing_separator => (false, "trailing separator"),
+        None if request.size.is_some() => (true, "sized"),
+        None => match request.config.kind {
+            Some(kind) => (kind == Kind::File, "configured kind"),
+            None => (infers_file(target, &request.config)?, "inferred from name"),
+        },
+        Some(Kind::File) if has_trailing_separator => {
+            return Err(Error::TypeConflict {
+                path: target.to_path_buf(),
+                reason: "File cannot have a trailing separator",
+            });
+        }
+        Some(Kind::File) => (true, "requested"),
+        Some(Kind::Directory) => (false, "requested"),
+    })
+}
+
+/// The error for `path` existing as the other kind than `is_file`, noting whether replacing it
+/// would remove entries and whether the wanted kind was only inferred.
+fn exists_as_other_kind(
+    fs: &impl Filesystem,
+    path: &Path,
+    target: &Path,
+    is_file: bool,
+    requested: Option<Kind>,
+) -> Result<Error> {
+    let (existing, wanted) = match is_file {
+        true => ("directory", "file"),
+        false => ("file", "directory"),
+    };
+    Ok(Error::ExistsAsOtherKind {
+        path: target.to_path_buf(),
+        existing,
+        wanted,
+        has_entries: is_file && !fs.read_dir(path)?.is_empty(),
+        inferred: requested.is_none(),
+    })
+}
+
+/// The existing entry whose name differs only in case from a component of `target`, relative to
+/// `root`, like `docs/Readme.md` for `docs/README.md`.
+fn case_collision(fs: &impl Filesystem, root: &Path, target: &Path) -> Result<Option<PathBuf>> {
+    let mut dir = root.to_path_buf();
+    for component in target.components() {
+        if let std::path::Component::Normal(name) = component
+            && fs.is_dir(&dir)
+        {
+            let entries = fs.read_dir(&dir)?;
+            if !entries.iter().any(|entry| entry == name) {
+                let lower = name.to_string_lossy().to_lowercase();
+                if let Some(entry) = entries
+                    .iter()
+                    .find(|entry| entry.to_string_lossy().to_lowercase() == lower)
+                {
+                    let existing = dir.join(entry);
+                    let existing = existing.strip_prefix(root).unwrap_or(&existing);
+                    return Ok(Some(existing.to_path_buf()));
+                }
+            }
+        }
+        dir.push(component);
+        if !fs.exists(&dir) {
+            break;
+        }
+    }
+    Ok(None)
+}
+
+fn is_executable_extension(ext: &str, config: &Config) -> bool {
+    config
+        .executable_extensions
+        .apply(EXECUTABLE_EXTENSIONS)
+        .contains(&ext)
+}
+
+/// Infers from its name whether `path` is meant to be a file.
+fn infers_file(path: &Path, config: &Config) -> anyhow::Result<bool> {
+    if let Some(ext) = path.extension() {
+        let mut patterns = globset::GlobSetBuilder::new();
+        let directory_extensions = config.directory_extensions.apply(DIRECTORY_EXTENSIONS);
+        for pattern in &directory_extensions {
+            patterns.add(
+                globset::Glob::new(pattern)
+                    .with_context(|| format!("Invalid directory extension {pattern}"))?,
+            );
+        }
+        let is_directory_extension = patterns.build()?.is_match(ext);
+        tracing::trace!(
+            ext = %ext.display(),
+            ?directory_extensions,
+            is_directory_extension,
+            "checked extension"
+        );
+        return Ok(!is_directory_extension);
+    }
+    let is_dotfile = path
+        .file_name()
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+    tracing::trace!(is_dotfile, dotfiles = ?config.dotfiles, "no extension");
+    Ok(is_dotfile && config.dotfiles == Some(Kind::File))
+}
+
+fn create_directory(
+    fs: &impl Filesystem,
+    path: &Path,
+    request: &CreateRequest,
+    mut contents: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if request.executable {
+        return Err(Error::TypeConflict {
+            path: path.to_path_buf(),
+            reason: "Directory cannot be executable",
+        });
+    }
+
+    if request.from.is_some() {
+        return Err(Error::TypeConflict {
+            path: path.to_path_buf(),
+            reason: "Directory cannot copy contents from a file",
+        });
+    }
+
+    let is_stdin_empty = contents.read(&mut [0; 1][..])? == 0;
+    if !is_stdin_empty {
+        return Err(Error::StdinForDirectory(path.to_path_buf()));
+    }
+
+    let first_created = created.len();
+    create_dir_all(fs, path, created)?;
+    if request.gitkeep {
+        write_gitkeeps(fs, created, first_created)?;
+    }
+    if request.git_init {
+        git::init(path)?;
+    }
+    Ok(())
+}
+
+fn create_file<F: Filesystem>(
+    fs: &F,
+    path: &Path,
+    request: &CreateRequest,
+    from: Option<&Path>,
+    mut contents: impl std::io::Read,
+    created: &mut Vec<PathBuf>,
+) -> Result<u64> {
+    if request.size.is_some() {
+        let is_stdin_empty = contents.read(&mut [0; 1][..])? == 0;
+        if !is_stdin_empty {
+            return Err(Error::StdinWithSize(path.to_path_buf()));
+        }
+    }
+    if let Some(from) = from {
+        if !fs.is_file(from) {
+            return Err(anyhow::anyhow!("No file {} to copy from", from.display()).into());
+        }
+        let is_stdin_empty = contents.read(&mut [0; 1][..])? == 0;
+        if !is_stdin_empty {
+            return Err(Error::StdinWithFrom(path.to_path_buf()));
+        }
+        if fs.exists(path) && fs.canonicalize(from)? == fs.canonicalize(path)? {
+            return Err(Error::CopyOntoItself(path.to_path_buf()));
+        }
+    }
+
+    let first_created = created.len();
+    create_dir_all(fs, path.parent().expect("joined with root"), created)?;
+    if request.gitkeep {
+        write_gitkeeps(fs, created, first_created)?;
+    }
+
+    let written;
+    if let Some(expected) = &request.verify {
+        if request.append && fs.is_file(path) {
+            return Err(anyhow::anyhow!("Cannot verify contents appended to a file").into());
+        }
+        let is_new = !fs.exists(path);
+        let result = replace_file(fs, path, |file, _| {
+            write_verified(file, contents, request, path, expected)
+        });
+        written = match result {
+            Ok(written) => written,
+            Err(e) => {
+                // Parents made for contents that didn't match go too.
+                if let Some(outermost) = created.get(first_created) {
+                    let _ = fs.remove_dir_all(outermost);
+                    created.truncate(first_created);
+                }
+                return Err(e);
+            }
+        };
+        if is_new {
+            created.push(path.to_path_buf());
+        }
+    } else if request.append && fs.is_file(path) {
+        tracing::debug!("appending to existing file");
+        let mut file = fs.open_append(path)?;
+        written = write_contents(&mut file, contents, request)?;
+    } else if request.overwrite && fs.is_file(path) {
+        // Copies go into the staging file too, so a failed one leaves the original alone.
+        written = replace_file(fs, path, |file, staging| match from {
+            Some(from) => copy_from(fs, from, staging),
+            None => write_contents(file, contents, request),
+        })?;
+    } else {
+        // Fails rather than truncating anything another process created since our own check.
+        let mut file = fs.create_new(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => Error::AlreadyExists(path.to_path_buf()),
+            _ => e.into(),
+        })?;
+        let result = match from {
+            Some(from) => copy_from(fs, from, path),
+            None => write_contents(&mut file, contents, request),
+        };
+        written = match result {
+            Ok(written) => written,
+            Err(e) => {
+                // Don't leave a partially written file behind.
+                drop(file);
+                let _ = fs.remove_file(path);
+                return Err(e);
+            }
+        };
+        created.push(path.to_path_buf());
+    }
+
+    let mut is_executable = request.executable;
+    if request.auto_exec
+        && let Some(ext) = path.extension()
+        && let Some(as_str) = ext.to_str()
+    {
+        is_executable |= is_executable_extension(as_str, &request.config);
+    }
+
+    if is_executable {
+        tracing::debug!(requested = request.executable, "marking executable");
+        fs.set_executable(path)?;
+    }
+
+    Ok(written)
+}
+
+/// Writes `contents` like [`write_contents`], failing unless they have the `expected` checksum.
+fn write_verified(
+    file: &mut impl WriteFile,
+    contents: impl std::io::Read,
+    request: &CreateRequest,
+    path: &Path,
+    expected: &checksum::Checksum,
+) -> Result<u64> {
+    let mut contents = checksum::HashingReader {
+        reader: contents,
+        hasher: Some(checksum::Hasher::new(expected.algorithm)),
+    };
+    let written = write_contents(file, &mut contents, request)?;
+    let actual = contents.hasher.expect("given above").finish();
+    if actual != *expected {
+        return Err(Error::ChecksumMismatch {
+            path: path.to_path_buf(),
+            expected: expected.clone(),
+            actual,
+        });
+    }
+    Ok(written)
+}
+
+/// Fills a newly created file with `contents`, or sizes it as requested, returning the bytes
+/// written.
+fn write_contents(
+    file: &mut impl WriteFile,
+    contents: impl std::io::Read,
+    request: &CreateRequest,
+) -> Result<u64> {
+    if let Some(size) = request.size {
+        tracing::debug!(size, fill = ?request.fill, request.preallocate, "sizing file");
+        if let Some(fill) = &request.fill {
+            fill::write(file, fill, size)?;
+            return Ok(size);
+        } else if request.preallocate {
+            file.preallocate(size)?;
+        } else {
+            // Extending with set_len leaves the file sparse where the filesystem supports it.
+            file.set_len(size)?;
+        }
+        Ok(0)
+    } else {
+        use std::io::Write;
+
+        let buffer_size = request
+            .buffer_size
+            .try_into()
+            .map_err(anyhow::Error::from)?;
+        let mut out = std::io::BufWriter::with_capacity(buffer_size, file);
+        let limit = request.max_bytes.unwrap_or(u64::MAX);
+        // Reading one byte past the limit tells an input of exactly --max-bytes from a longer one.
+        let copied = std::io::copy(&mut contents.take(limit.saturating_add(1)), &mut out)?;
+        if copied > limit {
+            return Err(Error::ContentsTooLong(limit));
+        }
+        out.flush()?;
+        tracing::debug!(bytes = copied, buffer_size, "wrote contents");
+        Ok(copied)
+    }
+}
+
+/// Fills the file `to` with the contents of `from`, returning the bytes copied.
+fn copy_from(fs: &impl Filesystem, from: &Path, to: &Path) -> Result<u64> {
+    tracing::debug!(from = %from.display(), "copying contents");
+    fs.copy_contents(from, to)
+        .with_context(|| format!("Unable to copy contents from {}", from.display()))?;
+    Ok(fs.file_len(to)?)
+}
+
+/// Runs `write` on a file next to `path`, given along with its own path, and renames it into
+/// place, so nothing half written is ever at `path` and the old contents, if any, survive a
+/// failure.
+fn replace_file<F: Filesystem, T>(
+    fs: &F,
+    path: &Path,
+    write: impl FnOnce(&mut F::File, &Path) -> Result<T>,
+) -> Result<T> {
+    let name = path
+        .file_name()
+        .expect("files have names")
+        .to_string_lossy();
+    let staging = path.with_file_name(format!(".{name}.mk-{:08x}", fastrand::u32(..)));
+    tracing::debug!(staging = %staging.display(), "replacing file");
+
+    let result = (|| {
+        let mut file = fs.create_new(&staging)?;
+        let written = write(&mut file, &staging)?;
+        file.sync_all()?;
+        drop(file);
+        if fs.exists(path) {
+            fs.copy_permissions(path, &staging)?;
+        }
+        fs.rename(&staging, path)?;
+        Ok(written)
+    })();
+    if result.is_err() {
+        let _ = fs.remove_file(&staging);
+    }
+    result
+}
+
+/// Fails if the filesystem holding `dir` has less than `size` bytes free for `target`. Sparse
+/// files take no space up front, so only contents that are actually written are checked.
+fn ensure_free_space(fs: &impl Filesystem, dir: &Path, target: &Path, size: u64) -> Result<()> {
+    let available = fs
+        .free_space(dir)
+        .with_context(|| format!("Unable to check free space in {}", dir.display()))?;
+    if let Some(available) = available
+        && size > available
+    {
+        return Err(Error::NotEnoughSpace {
+            path: target.to_path_buf(),
+            needed: size,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Creates `path` and all missing parents, recording the directories that did not exist before
+/// in `created`, outermost first.
+fn create_dir_all(fs: &impl Filesystem, path: &Path, created: &mut Vec<PathBuf>) -> Result<()> {
+    let first_created = created.len();
+    created.extend(
+        path.ancestors()
+            .take_while(|p| !p.as_os_str().is_empty() && !fs.exists(p))
+            .map(Path::to_path_buf),
+    );
+    created[first_created..].reverse();
+    for dir in &created[first_created..] {
+        tracing::debug!(dir = %dir.display(), "creating directory");
+    }
+
+    fs.create_dir_all(path)?;
+    Ok(())
+}
+
+/// Writes a .gitkeep into each directory created since `first_created`, recording it too.
+fn write_gitkeeps(
+    fs: &impl Filesystem,
+    created: &mut Vec<PathBuf>,
+    first_created: usize,
+) -> Result<()> {
+    let gitkeeps = created[first_created..]
+        .iter()
+        .map(|dir| dir.join(".gitkeep"))
+        .collect::<Vec<_>>();
+    for gitkeep in gitkeeps {
+        fs.create_new(&gitkeep)?;
+        created.push(gitkeep);
+    }
+    Ok(())
+}
+
+/// Fails if `target` would resolve outside of `root`.
+fn ensure_within(fs: &impl Filesystem, root: &Path, target: &Path) -> Result<()> {
+    let outside = || Error::OutsideRoot {
+        path: target.to_path_buf(),
+        root: root.to_path_buf(),
+    };
+
+    let mut depth = 0usize;
+    for component in target.components() {
+        depth = match component {
+            std::path::Component::Normal(_) => depth + 1,
+            std::path::Component::CurDir => depth,
+            std::path::Component::ParentDir => depth.checked_sub(1).ok_or_else(outside)?,
+            _ => return Err(outside()),
+        };
+    }
+
+    // Symlinks in the existing part of the path could still lead elsewhere.
+    let root = fs.canonicalize(root)?;
+    let path = root.join(target);
+    let existing = fs.canonicalize(
+        path.ancestors()
+            .find(|p| fs.exists(p))
+            .expect("root exists"),
+    )?;
+    if !existing.starts_with(&root) {
+        return Err(outside());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_requested_entry() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let request = CreateRequest::new("notes/{n:02}-idea.md").gitkeep(true);
+
+        let mut created = Vec::new();
+        let entry = create(dir.path(), &request, "contents".as_bytes(), &mut created)?;
+
+        assert_eq!(entry.resolved, Path::new("notes/01-idea.md"));
+        assert!(entry.is_file);
+        assert_eq!(std::fs::read_to_string(&entry.path)?, "contents");
+        assert_eq!(
+            created,
+            [
+                dir.path().join("notes"),
+                dir.path().join("notes/.gitkeep"),
+                entry.path
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_failure_kinds() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("foo.txt"), "")?;
+        let mut created = Vec::new();
+
+        let mut create = |request| create(dir.path(), &request, &[][..], &mut created);
+        assert!(matches!(
+            create(CreateRequest::new("foo.txt")),
+            Err(Error::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            create(CreateRequest::new("foo.txt/bar.txt")),
+            Err(Error::ParentIsFile { .. })
+        ));
+        assert!(matches!(
+            create(CreateRequest::new("bar.txt/").kind(Kind::File)),
+            Err(Error::TypeConflict { .. })
+        ));
+        assert!(matches!(
+            create(CreateRequest::new("../escaped.txt").restrict(true)),
+            Err(Error::OutsideRoot { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn creates_entries_in_memory() -> anyhow::Result<()> {
+        let fs = MemoryFs::new();
+        let root = Path::new("/project");
+        fs.create_dir_all(root)?;
+        let mut created = Vec::new();
+
+        let mut create = |request, contents: &str| {
+            create_in(&fs, root, &request, contents.as_bytes(), &mut created)
+        };
+        create(CreateRequest::new("bin/run.sh"), "echo hi")?;
+        create(CreateRequest::new("bin/run.sh").overwrite(true), "echo bye")?;
+        create(
+            CreateRequest::new("bin/run.sh").append(true),
+            "\necho again",
+        )?;
+        create(CreateRequest::new("logs/out.txt").append(true), "new")?;
+        create(
+            CreateRequest::new("logs/copy.txt").from(PathBuf::from("logs/out.txt")),
+            "",
+        )?;
+        create(CreateRequest::new("docs/{n}-intro.md"), "")?;
+        let entry = create(CreateRequest::new("docs/{n}-usage.md"), "")?;
+
+        assert_eq!(entry.resolved, Path::new("docs/2-usage.md"));
+        assert_eq!(
+            fs.read(&root.join("bin/run.sh")).as_deref(),
+            Some(&b"echo bye\necho again"[..])
+        );
+        assert_eq!(
+            fs.read(&root.join("logs/out.txt")).as_deref(),
+            Some(&b"new"[..])
+        );
+        assert_eq!(
+            fs.read(&root.join("logs/copy.txt")).as_deref(),
+            Some(&b"new"[..])
+        );
+        assert!(fs.is_executable(&root.join("bin/run.sh")));
+        assert_eq!(fs.read_dir(&root.join("bin"))?, ["run.sh"]);
+        assert!(matches!(
+            create(CreateRequest::new("bin/run.sh/extra.txt"), ""),
+            Err(Error::ParentIsFile { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_names_differing_in_case() -> anyhow::Result<()> {
+        let fs = MemoryFs::new();
+        let root = Path::new("/project");
+        fs.create_dir_all(root)?;
+        let mut created = Vec::new();
+        let mut create = |path: &str| {
+            let request = CreateRequest::new(path).check_case(true);
+            create_in(&fs, root, &request, &[][..], &mut created)
+        };
+        create("docs/Readme.md")?;
+
+        match create("docs/README.md") {
+            Err(Error::CaseCollision { existing, .. }) => {
+                assert_eq!(existing, Path::new("docs/Readme.md"))
+            }
+            other => panic!("expected a case collision, got {other:?}"),
+        }
+        assert!(matches!(
+            create("Docs/guide.md"),
+            Err(Error::CaseCollision { .. })
+        ));
+        assert!(matches!(
+            create("docs/Readme.md"),
+            Err(Error::AlreadyExists(_))
+        ));
+        create("docs/guide.md")?;
+        Ok(())
+    }
+
+    #[test]
+    fn reports_bytes_written() -> anyhow::Result<()> {
+        let fs = MemoryFs::new();
+        let root = Path::new("/project");
+        fs.create_dir_all(root)?;
+        let mut created = Vec::new();
+        let mut create = |request: CreateRequest, contents: &str| {
+            create_in(&fs, root, &request, contents.as_bytes(), &mut created)
+                .map(|entry| entry.written)
+        };
+
+        assert_eq!(create(CreateRequest::new("a.txt"), "abc")?, 3);
+        assert_eq!(create(CreateRequest::new("a.txt").append(true), "de")?, 2);
+        assert_eq!(
+            create(CreateRequest::new("sparse.bin").size(1 << 20), "")?,
+            0
+        );
+        assert_eq!(create(CreateRequest::new("dir"), "")?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn moves_contents_into_place_once_verified() -> anyhow::Result<()> {
+        let fs = MemoryFs::new();
+        let root = Path::new("/project");
+        fs.create_dir_all(root)?;
+        let mut created = Vec::new();
+        let abc = checksum::parse(
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        )?;
+        let mut create = |path: &str, contents: &str| {
+            let request = CreateRequest::new(path).verify(abc.clone());
+            create_in(&fs, root, &request, contents.as_bytes(), &mut created)
+        };
+
+        create("dl/ok.bin", "abc")?;
+        assert!(matches!(
+            create("dl/bad.bin", "abd"),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        assert!(create("new/bad.bin", "abd").is_err());
+        assert!(!fs.exists(&root.join("new")));
+
+        assert_eq!(
+            fs.read(&root.join("dl/ok.bin")).as_deref(),
+            Some(&b"abc"[..])
+        );
+        assert_eq!(fs.read_dir(&root.join("dl"))?, ["ok.bin"]);
+        assert_eq!(created, [root.join("dl"), root.join("dl/ok.bin")]);
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_truncate_file_created_after_check() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("foo.txt");
+        let request = CreateRequest::new("foo.txt");
+        std::fs::write(&path, "other process")?;
+
+        let mut created = Vec::new();
+        let err = create_file(
+            &RealFs,
+            &path,
+            &request,
+            None,
+            "ours".as_bytes(),
+            &mut created,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, Error::AlreadyExists(_)));
+        assert_eq!(std::fs::read_to_string(&path)?, "other process");
+        assert!(created.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// Applies `f` to the final component of `path`, keeping any trailing separator.
+fn map_file_name(path: &Path, f: impl FnOnce(&str) -> String) -> PathBuf {
+    let Some(s) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    let trimmed = s.trim_end_matches(std::path::is_separator);
+    let start = trimmed
+        .rfind(std::path::is_separator)
+        .map_or(0, |sep| sep + 1);
+
+    format!(
+        "{}{}{}",
+        &s[..start],
+        f(&trimmed[start..]),
+        &s[trimmed.len()..]
+    )
+    .into()
+}
+
+/// Turns the final component of `path` into a lowercase, ASCII, dash-separated slug, keeping its
+/// lowercased extension and any leading '.'.
+pub fn slug(path: &Path) -> PathBuf {
+    map_file_name(path, |name| {
+        let (dot, name) = match name.strip_prefix('.') {
+            Some(rest) => (".", rest),
+            None => ("", name),
+        };
+        let (stem, ext) = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+            _ => (name, None),
+        };
+
+        let mut slug = dot.to_string();
+        slug += &deunicode::deunicode(stem)
+            .to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        if let Some(ext) = ext {
+            slug.push('.');
+            slug.push_str(&ext.to_lowercase());
+        }
+        slug
+    })
+}
+
+/// Replaces characters that can't appear in a file name on some platform with '_', and trims the
+/// trailing dots and spaces Windows drops.
+pub fn sanitize_component(name: &str) -> String {
+    let sanitized = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' | '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    sanitized.trim_end_matches(['.', ' ']).to_string()
+}
+
+/// Sanitizes the final component of `path` with [`sanitize_component`].
+pub fn sanitize(path: &Path) -> PathBuf {
+    map_file_name(path, sanitize_component)
+}
+
+/// Finds a component of `path` that Windows treats as a device, like `CON` or `aux.txt`.
+pub fn reserved_component(path: &Path) -> Option<&str> {
+    path.components().find_map(|component| {
+        let name = component.as_os_str().to_str()?;
+        let stem = name.split('.').next()?.trim_end_matches(' ');
+        let is_reserved = matches!(
+            stem.to_ascii_uppercase().as_str(),
+            "CON" | "PRN" | "AUX" | "NUL" | "CONIN$" | "CONOUT$"
+        ) || ["COM", "LPT"].iter().any(|prefix| {
+            let stem = stem.as_bytes();
+            stem.len() == 4
+                && stem[..3].eq_ignore_ascii_case(prefix.as_bytes())
+                && matches!(stem[3], b'1'..=b'9')
+        });
+        is_reserved.then_some(name)
+    })
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Normalization {
+    /// Composed form, as used by most Linux and Windows tools.
+    Nfc,
+    /// Decomposed form, as historically produced by macOS.
+    Nfd,
+}
+
+/// Converts `path` to the given Unicode normalization form.
+pub fn normalize(path: &Path, form: Normalization) -> PathBuf {
+    let Some(s) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    match form {
+        Normalization::Nfc => s.nfc().collect::<String>().into(),
+        Normalization::Nfd => s.nfd().collect::<String>().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugifies_file_name() {
+        assert_eq!(
+            slug(Path::new("posts/Hello, World! Part 2.md")),
+            Path::new("posts/hello-world-part-2.md")
+        );
+        assert_eq!(slug(Path::new("Post.MD")), Path::new("post.md"));
+    }
+
+    #[test]
+    fn transliterates_unicode() {
+        assert_eq!(
+            slug(Path::new("Crème Brûlée Über")),
+            Path::new("creme-brulee-uber")
+        );
+    }
+
+    #[test]
+    fn leaves_parents_and_trailing_separator() {
+        assert_eq!(
+            slug(Path::new("My Notes/Big Idea/")).as_os_str(),
+            "My Notes/big-idea/"
+        );
+    }
+
+    #[test]
+    fn keeps_leading_dot() {
+        assert_eq!(slug(Path::new(".Env Local")), Path::new(".env-local"));
+    }
+
+    #[test]
+    fn sanitizes_file_name() {
+        assert_eq!(
+            sanitize(Path::new("downloads/What? A \"title\": part 1|2.txt")),
+            Path::new("downloads/What_ A _title__ part 1_2.txt")
+        );
+    }
+
+    #[test]
+    fn sanitizes_control_characters_and_trailing_dots() {
+        assert_eq!(sanitize_component("tab\tthere. ."), "tab_there");
+    }
+
+    #[test]
+    fn sanitizes_separators_in_components() {
+        assert_eq!(sanitize_component("feature/branch"), "feature_branch");
+    }
+
+    #[test]
+    fn finds_reserved_names() {
+        assert_eq!(reserved_component(Path::new("src/CON")), Some("CON"));
+        assert_eq!(reserved_component(Path::new("nul/foo.txt")), Some("nul"));
+        assert_eq!(
+            reserved_component(Path::new("docs/aux.txt")),
+            Some("aux.txt")
+        );
+        assert_eq!(
+            reserved_component(Path::new("com1.tar.gz")),
+            Some("com1.tar.gz")
+        );
+        assert_eq!(reserved_component(Path::new("Lpt9")), Some("Lpt9"));
+    }
+
+    #[test]
+    fn allows_similar_names() {
+        assert_eq!(reserved_component(Path::new("console/auxiliary.txt")), None);
+        assert_eq!(reserved_component(Path::new("COM0.txt")), None);
+        assert_eq!(reserved_component(Path::new("lpt0")), None);
+        assert_eq!(reserved_component(Path::new("com10/lpt.txt")), None);
+        assert_eq!(reserved_component(Path::new("a€")), None);
+    }
+
+    #[test]
+    fn normalizes_unicode() {
+        let composed = "caf\u{e9}/r\u{e9}sum\u{e9}.md";
+        let decomposed = "cafe\u{301}/re\u{301}sume\u{301}.md";
+
+        assert_eq!(
+            normalize(Path::new(decomposed), Normalization::Nfc),
+            Path::new(composed)
+        );
+        assert_eq!(
+            normalize(Path::new(composed), Normalization::Nfd),
+            Path::new(decomposed)
+        );
+    }
+}
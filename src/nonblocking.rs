@@ -0,0 +1,45 @@
+use crate::{CreateRequest, Entry, Result};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncRead;
+use tokio_util::io::SyncIoBridge;
+
+/// Like [`create`](crate::create), but runs on tokio's blocking pool and reads `contents`
+/// asynchronously, so async tools can make entries without stalling their runtime.
+///
+/// Must be called from within a tokio runtime.
+pub async fn create_async(
+    root: &Path,
+    request: &CreateRequest,
+    contents: impl AsyncRead + Unpin + Send + 'static,
+    created: &mut Vec<PathBuf>,
+) -> Result<Entry> {
+    let root = root.to_path_buf();
+    let request = request.clone();
+    let (result, made) = tokio::task::spawn_blocking(move || {
+        let mut made = Vec::new();
+        let result = crate::create(&root, &request, SyncIoBridge::new(contents), &mut made);
+        (result, made)
+    })
+    .await
+    .map_err(anyhow::Error::from)?;
+    created.extend(made);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn creates_without_blocking() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let request = CreateRequest::new("notes/idea.md");
+
+        let mut created = Vec::new();
+        let entry = create_async(dir.path(), &request, &b"contents"[..], &mut created).await?;
+
+        assert_eq!(std::fs::read_to_string(&entry.path)?, "contents");
+        assert_eq!(created, [dir.path().join("notes"), entry.path]);
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Windows refuses paths longer than this unless they have the extended-length prefix.
+const MAX_PATH: usize = 260;
+
+/// Longest name a single path component may have on common filesystems, in bytes.
+const MAX_NAME: usize = 255;
+
+/// Longest full path the platform accepts, in bytes.
+const MAX_TOTAL: usize = if cfg!(windows) {
+    32_767
+} else if cfg!(target_os = "macos") {
+    1024
+} else {
+    4096
+};
+
+/// Fails if `path` or any of its components is too long for the platform.
+pub fn check_limits(path: &Path) -> crate::Result<()> {
+    if let Some(component) = path
+        .components()
+        .map(Component::as_os_str)
+        .find(|name| name.len() > MAX_NAME)
+    {
+        return Err(crate::Error::NameTooLong {
+            name: component.display().to_string(),
+            limit: MAX_NAME,
+        });
+    }
+    if path.as_os_str().len() > MAX_TOTAL {
+        return Err(crate::Error::PathTooLong {
+            path: path.to_path_buf(),
+            limit: MAX_TOTAL,
+        });
+    }
+    Ok(())
+}
+
+/// Counts the named components of `path`.
+pub fn depth(path: &Path) -> usize {
+    path.components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .count()
+}
+
+/// Reserves `size` bytes of real blocks for `file`, so later writes can't run out of space. Falls
+/// back to writing zeros where the filesystem can't preallocate.
+pub fn preallocate(file: &mut std::fs::File, size: u64) -> std::io::Result<()> {
+    if allocate(file, size).is_ok() {
+        return file.set_len(size);
+    }
+
+    use std::io::Write;
+    let zeros = [0; 64 * 1024];
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..len])?;
+        remaining -= len as u64;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn allocate(file: &std::fs::File, size: u64) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let len = libc::off_t::try_from(size).map_err(std::io::Error::other)?;
+    // SAFETY: the descriptor is owned by `file` and stays open for the call.
+    match unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, len) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn allocate(file: &std::fs::File, size: u64) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let mut store = libc::fstore_t {
+        fst_flags: libc::F_ALLOCATEALL,
+        fst_posmode: libc::F_PEOFPOSMODE,
+        fst_offset: 0,
+        fst_length: libc::off_t::try_from(size).map_err(std::io::Error::other)?,
+        fst_bytesalloc: 0,
+    };
+    // SAFETY: the descriptor is owned by `file` and `store` outlives the call.
+    match unsafe { libc::fcntl(file.as_raw_fd(), libc::F_PREALLOCATE, &mut store) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn allocate(_file: &std::fs::File, _size: u64) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Fills the existing file `to` with the contents of `from`, sharing its blocks on copy-on-write
+/// filesystems like Btrfs, XFS, and APFS so even huge files copy instantly and take no space.
+/// Falls back to a streamed copy elsewhere. `to` keeps its own permissions either way.
+pub fn copy_contents(from: &Path, to: &Path) -> std::io::Result<()> {
+    match clone(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) => tracing::debug!(error = %e, "unable to clone, copying instead"),
+    }
+    let mut source = std::fs::File::open(from)?;
+    let mut target = std::fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(to)?;
+    std::io::copy(&mut source, &mut target)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn clone(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = std::fs::File::open(from)?;
+    let target = std::fs::OpenOptions::new().write(true).open(to)?;
+    // SAFETY: both descriptors are owned by files that stay open for the call.
+    match unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// `clonefile` only makes new files, so the clone is made beside `to` and renamed over it.
+#[cfg(target_os = "macos")]
+fn clone(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let permissions = std::fs::metadata(to)?.permissions();
+    let name = to.file_name().unwrap_or_default().to_string_lossy();
+    let staging = to.with_file_name(format!(".{name}.mk-{:08x}", fastrand::u32(..)));
+    let source = std::ffi::CString::new(from.as_os_str().as_bytes())?;
+    let target = std::ffi::CString::new(staging.as_os_str().as_bytes())?;
+    // SAFETY: both paths are NUL-terminated and outlive the call.
+    if unsafe { libc::clonefile(source.as_ptr(), target.as_ptr(), 0) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let result = std::fs::set_permissions(&staging, permissions)
+        .and_then(|()| std::fs::rename(&staging, to));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&staging);
+    }
+    result
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn clone(_from: &Path, _to: &Path) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `path`, if the platform can
+/// tell.
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> std::io::Result<Option<u64>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stat` is only read after the call fills it in.
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: statvfs succeeded, so `stat` is initialized.
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast, clippy::useless_conversion)]
+    Ok(Some(u64::from(stat.f_bavail) * u64::from(stat.f_frsize)))
+}
+
+#[cfg(not(unix))]
+pub fn free_space(_path: &Path) -> std::io::Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Opens `dir` in the platform's file manager without waiting for it.
+pub fn reveal(dir: &Path) -> std::io::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener)
+        .arg(dir)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Makes a symbolic link at `link` pointing to `target`.
+#[cfg(unix)]
+pub fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Windows links to files and directories differently, so `target` has to exist to pick one,
+/// resolved from the link's directory like the link itself will be.
+#[cfg(windows)]
+pub fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    let resolved = link.parent().unwrap_or(Path::new("")).join(target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// WASI's symlink call is still unstable in std.
+#[cfg(not(any(unix, windows)))]
+pub fn symlink(_target: &Path, _link: &Path) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Adds the `\\?\` extended-length prefix to long absolute paths on Windows, so deep trees can be
+/// created. Elsewhere the path is returned unchanged.
+pub fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) || !path.is_absolute() || path.as_os_str().len() < MAX_PATH {
+        return path.to_path_buf();
+    }
+    match path.to_str() {
+        Some(s) => with_extended_length_prefix(s).into(),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Prefixes an absolute Windows path with `\\?\`. The prefix turns off Windows' own path
+/// normalization, so separators are unified and `.`/`..` resolved here instead.
+fn with_extended_length_prefix(path: &str) -> String {
+    if path.starts_with(r"\\?\") {
+        return path.to_string();
+    }
+    let (prefix, rest) = match path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//")) {
+        Some(unc) => (r"\\?\UNC\", unc),
+        None => (r"\\?\", path),
+    };
+
+    let mut components = Vec::new();
+    for component in rest.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." if components.len() > 1 => {
+                components.pop();
+            }
+            ".." => {}
+            component => components.push(component),
+        }
+    }
+    format!("{prefix}{}", components.join(r"\"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_drive_paths() {
+        assert_eq!(
+            with_extended_length_prefix(r"C:\Users\me/deep\.\tree\..\file.txt"),
+            r"\\?\C:\Users\me\deep\file.txt"
+        );
+    }
+
+    #[test]
+    fn prefixes_unc_paths() {
+        assert_eq!(
+            with_extended_length_prefix(r"\\server\share\deep\file.txt"),
+            r"\\?\UNC\server\share\deep\file.txt"
+        );
+    }
+
+    #[test]
+    fn keeps_prefixed_paths() {
+        assert_eq!(
+            with_extended_length_prefix(r"\\?\C:\already\there"),
+            r"\\?\C:\already\there"
+        );
+    }
+
+    #[test]
+    fn does_not_pop_past_root() {
+        assert_eq!(
+            with_extended_length_prefix(r"C:\..\..\file.txt"),
+            r"\\?\C:\file.txt"
+        );
+    }
+
+    #[test]
+    fn leaves_short_paths() {
+        assert_eq!(long_path(Path::new("/short")), Path::new("/short"));
+    }
+
+    #[test]
+    fn rejects_long_components() {
+        let long = "a".repeat(256);
+
+        let err = check_limits(&Path::new("ok").join(&long).join("ok")).unwrap_err();
+        assert!(err.to_string().contains(&long));
+        assert!(check_limits(&Path::new("ok").join("a".repeat(255))).is_ok());
+    }
+
+    #[test]
+    fn rejects_long_paths() {
+        let path = std::iter::repeat_n("abcdefgh", MAX_TOTAL / 8 + 1).collect::<PathBuf>();
+
+        assert!(check_limits(&path).is_err());
+    }
+
+    #[test]
+    fn counts_depth() {
+        assert_eq!(depth(Path::new("a/./b/c.txt")), 3);
+        assert_eq!(depth(Path::new("/abs")), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reports_free_space() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert!(free_space(dir.path())?.is_some());
+        assert!(free_space(&dir.path().join("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn copies_contents_into_existing_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (seed, copy) = (dir.path().join("seed.img"), dir.path().join("copy.img"));
+        std::fs::write(&seed, vec![7; 3 * 4096])?;
+        std::fs::write(&copy, "")?;
+        let permissions = std::fs::metadata(&copy)?.permissions();
+
+        copy_contents(&seed, &copy)?;
+
+        assert_eq!(std::fs::read(&copy)?, vec![7; 3 * 4096]);
+        assert_eq!(std::fs::metadata(&copy)?.permissions(), permissions);
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn preallocates_blocks() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut file = std::fs::File::create(dir.path().join("disk.img"))?;
+
+        preallocate(&mut file, 1024 * 1024)?;
+
+        let metadata = file.metadata()?;
+        assert_eq!(metadata.len(), 1024 * 1024);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert!(metadata.blocks() * 512 >= metadata.len());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::Context;
+
+/// Renders the `{{name}}` and `{{name:default}}` variables in `template`, asking `lookup` for
+/// each value along with its default.
+pub fn render(
+    template: &str,
+    mut lookup: impl FnMut(&str, Option<&str>) -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .with_context(|| format!("Unclosed {{{{ in template: {}", &rest[start..]))?;
+        let spec = &rest[start + 2..start + end];
+        let (name, default) = match spec.split_once(':') {
+            Some((name, default)) => (name.trim(), Some(default.trim())),
+            None => (spec.trim(), None),
+        };
+        anyhow::ensure!(
+            !name.is_empty(),
+            "Template variable without a name: {{{{{spec}}}}}"
+        );
+        rendered.push_str(&lookup(name, default)?);
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// The variables in `template` in order of first use, each with its first default.
+pub fn variables(template: &str) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    let mut variables = Vec::<(String, Option<String>)>::new();
+    render(template, |name, default| {
+        match variables.iter_mut().find(|(n, _)| n == name) {
+            Some((_, known)) => {
+                if known.is_none() {
+                    *known = default.map(str::to_string);
+                }
+            }
+            None => variables.push((name.to_string(), default.map(str::to_string))),
+        }
+        Ok(String::new())
+    })?;
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_variables() -> anyhow::Result<()> {
+        let rendered = render(
+            "# {{ title }}\nby {{author:anonymous}}\n",
+            |name, default| {
+                Ok(match name {
+                    "title" => "Hello".to_string(),
+                    _ => default.unwrap_or_default().to_string(),
+                })
+            },
+        )?;
+
+        assert_eq!(rendered, "# Hello\nby anonymous\n");
+        Ok(())
+    }
+
+    #[test]
+    fn lists_variables_once() -> anyhow::Result<()> {
+        assert_eq!(
+            variables("{{title}} by {{ author }} on {{date:today}}, {{author:me}}")?,
+            [
+                ("title".to_string(), None),
+                ("author".to_string(), Some("me".to_string())),
+                ("date".to_string(), Some("today".to_string())),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors_with_malformed_variables() {
+        let lookup = |_: &str, _: Option<&str>| Ok(String::new());
+        assert!(render("{{title", lookup).is_err());
+        assert!(render("{{ }}", lookup).is_err());
+    }
+}
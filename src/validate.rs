@@ -0,0 +1,181 @@
+use std::path::Path;
+
+/// Structured formats whose contents can be checked before they are written.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// The format files like `path` hold, by extension.
+    pub fn of(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+        })
+    }
+}
+
+/// Where and why contents failed to parse, with 1-based lines and columns in characters.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("line {line}, column {column}: {message}")]
+pub struct Invalid {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Checks that `contents` are syntactically valid `format`.
+pub fn check(format: Format, contents: &[u8]) -> Result<(), Invalid> {
+    let text = std::str::from_utf8(contents).map_err(|e| {
+        let (line, column) = position(contents, e.valid_up_to());
+        Invalid {
+            line,
+            column,
+            message: "invalid UTF-8".to_string(),
+        }
+    })?;
+    match format {
+        Format::Json => json(text),
+        Format::Yaml => yaml(text),
+        Format::Toml => match toml::from_str::<toml::Table>(text) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let (line, column) = position(contents, e.span().map_or(0, |span| span.start));
+                Err(Invalid {
+                    line,
+                    column,
+                    message: e.message().to_string(),
+                })
+            }
+        },
+    }
+}
+
+/// The line and column of byte `offset` in `contents`.
+fn position(contents: &[u8], offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line_start = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    let column = String::from_utf8_lossy(&before[line_start..])
+        .chars()
+        .count();
+    (
+        before.iter().filter(|&&b| b == b'\n').count() + 1,
+        column + 1,
+    )
+}
+
+fn json(text: &str) -> Result<(), Invalid> {
+    let Err(e) = serde_json::from_str::<serde::de::IgnoredAny>(text) else {
+        return Ok(());
+    };
+    // serde_json counts columns in bytes, so they are recounted in characters.
+    let line_start = text
+        .split_inclusive('\n')
+        .take(e.line().saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+    let (line, column) = position(text.as_bytes(), line_start + e.column().saturating_sub(1));
+    let message = e.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(&*message, |(message, _)| message);
+    Err(Invalid {
+        line,
+        column,
+        message: message.to_string(),
+    })
+}
+
+fn yaml(text: &str) -> Result<(), Invalid> {
+    for event in saphyr_parser::Parser::new_from_str(text) {
+        if let Err(e) = event {
+            return Err(Invalid {
+                line: e.marker().line(),
+                column: e.marker().col() + 1,
+                message: e.info().to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(format: Format, contents: &str) -> Option<(usize, usize)> {
+        check(format, contents.as_bytes())
+            .err()
+            .map(|e| (e.line, e.column))
+    }
+
+    #[test]
+    fn checks_json() {
+        let valid =
+            r#"{"name": "mk", "tags": [1, -2.5e3, true, null], "nested": {"é": "\u00e9\n"}}"#;
+        assert_eq!(invalid(Format::Json, valid), None);
+        assert_eq!(invalid(Format::Json, "{\n  \"a\": 1,\n}"), Some((3, 1)));
+        assert_eq!(invalid(Format::Json, "[1, 2"), Some((1, 5)));
+        assert_eq!(invalid(Format::Json, "{\"a\": 01}"), Some((1, 8)));
+        assert_eq!(invalid(Format::Json, "{} {}"), Some((1, 4)));
+        assert_eq!(invalid(Format::Json, "\"tab\there\""), Some((1, 4)));
+        assert_eq!(invalid(Format::Json, "{\"é\": x}"), Some((1, 7)));
+        assert!(invalid(Format::Json, &"[".repeat(1000)).is_some());
+    }
+
+    #[test]
+    fn checks_toml() {
+        assert_eq!(invalid(Format::Toml, "[package]\nname = \"mk\"\n"), None);
+        assert_eq!(invalid(Format::Toml, "[package]\nname = \n"), Some((2, 8)));
+    }
+
+    #[test]
+    fn checks_yaml() {
+        let valid = "\
+name: it's fine # a comment
+list: [a, 'b, c', {d: e}]
+items:
+  - &first \"quoted
+    across lines\"
+  - a[0]
+script: |
+  echo ]
+  if [ -n \"$x ]; then :; fi
+";
+        assert_eq!(invalid(Format::Yaml, valid), None);
+        assert_eq!(invalid(Format::Yaml, "a:\n\tb: c\n"), Some((2, 2)));
+        assert_eq!(
+            invalid(Format::Yaml, "list: [a, b\nnext: c\n"),
+            Some((2, 5))
+        );
+        assert_eq!(invalid(Format::Yaml, "list: [a, b}\n"), Some((1, 12)));
+        assert_eq!(invalid(Format::Yaml, "name: \"open\n"), Some((1, 7)));
+        assert_eq!(invalid(Format::Yaml, "a: b: c\n"), Some((1, 5)));
+        assert_eq!(invalid(Format::Yaml, "a:\n  - b\n c: d\n"), Some((3, 2)));
+    }
+
+    #[test]
+    fn picks_formats_by_extension() {
+        assert_eq!(Format::of(Path::new("config/app.YML")), Some(Format::Yaml));
+        assert_eq!(Format::of(Path::new("package.json")), Some(Format::Json));
+        assert_eq!(Format::of(Path::new("notes.txt")), None);
+    }
+}